@@ -4,11 +4,22 @@ use xml_client::XmlClient;
 mod xml_node;
 use game_sdk::gamestate::GameState;
 use game_sdk::player::Player;
+use std::sync::Arc;
 //use player::random::RandomPlayer as Algorithm;
+use player::minimax::cache::DEFAULT_HASH_SIZE;
+use player::minimax::evaluation::{evaluate_with_trace, EvaluationParameters};
+use player::minimax::evaluator::{Evaluator, NeuralEvaluator, StaticEvaluator};
+use player::minimax::nnue::NeuralNetwork;
 use player::minimax::search::Searcher as Algorithm;
+use player::minimax::search::MAX_SEARCH_DEPTH;
+use player::minimax::search_info::InfoLinePrinter;
+use player::minimax::selectivity::SelectiveSearch;
 //use player::greedy::GreedyPlayer as Algorithm;
 
-fn run_test(mut player: Box<dyn Player>) {
+// Reads FENs from stdin and answers with the chosen action.
+// "analyze <lines> <fen>" prints the best lines of the position instead.
+// "eval <fen>" prints the static evaluation of the position feature by feature.
+fn run_test<E: Evaluator>(player: &mut Algorithm<E>, parameters: &EvaluationParameters) {
     loop {
         let mut input = String::new();
         std::io::stdin()
@@ -20,48 +31,232 @@ fn run_test(mut player: Box<dyn Player>) {
             println!("reset");
             continue;
         }
+        if let Some(command) = input.strip_prefix("analyze ") {
+            let (lines, fen) = command
+                .split_once(' ')
+                .expect("Usage: analyze <lines> <fen>");
+            let lines: usize = lines.parse().expect("Invalid number of lines");
+            let infos = player.analyze(&GameState::from_fen(fen), lines);
+            for info in infos.iter() {
+                println!("line {}: {}", info.multi_pv, info.serialize());
+            }
+            println!("analysis done");
+            continue;
+        }
+        if let Some(fen) = input.strip_prefix("eval ") {
+            let trace = evaluate_with_trace(&GameState::from_fen(fen), parameters);
+            println!("{}", trace);
+            continue;
+        }
         let state = GameState::from_fen(&input.clone());
         let action = player.on_move_request(&state);
         println!("action: {}", action.serialize());
     }
 }
 
-fn main() {
-    let mut host = "localhost".to_string();
-    let mut port = "13050".to_string();
-    let mut reservation = "".to_string();
-    let mut test = false;
-    let mut time_limit: u64 = 1980;
+// Command line options
+struct Options {
+    host: String,
+    port: String,
+    reservation: String,
+    test: bool,
+    time_limit: u64,
+    time_management: bool,
+    aspiration_windows: bool,
+    endgame_solver: bool,
+    threads: usize,
+    hash_size: usize,
+    max_depth: usize,
+    max_nodes: usize,
+    selective_search: SelectiveSearch,
+    parameters_path: String,
+    parameters_json: String,
+    network_path: String,
+    info_lines: bool,
+}
 
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            host: "localhost".to_string(),
+            port: "13050".to_string(),
+            reservation: "".to_string(),
+            test: false,
+            time_limit: 1980,
+            time_management: true,
+            aspiration_windows: true,
+            endgame_solver: true,
+            threads: 1,
+            hash_size: DEFAULT_HASH_SIZE,
+            max_depth: MAX_SEARCH_DEPTH,
+            max_nodes: usize::MAX,
+            selective_search: SelectiveSearch::default(),
+            parameters_path: "".to_string(),
+            parameters_json: "".to_string(),
+            network_path: "".to_string(),
+            info_lines: false,
+        }
+    }
+}
+
+fn main() {
+    let mut options = Options::default();
     {
         let mut parser = ArgumentParser::new();
         parser
-            .refer(&mut host)
+            .refer(&mut options.host)
             .add_option(&["-h", "--host"], Store, "Host");
         parser
-            .refer(&mut port)
+            .refer(&mut options.port)
             .add_option(&["-p", "--port"], Store, "Port");
-        parser
-            .refer(&mut reservation)
-            .add_option(&["-r", "--reservation"], Store, "Reservation");
-        parser.refer(&mut test).add_option(
+        parser.refer(&mut options.reservation).add_option(
+            &["-r", "--reservation"],
+            Store,
+            "Reservation",
+        );
+        parser.refer(&mut options.test).add_option(
             &["-T", "--test"],
             Store,
             "Run the client in test mode.",
         );
+        parser.refer(&mut options.time_limit).add_option(
+            &["-t", "--time"],
+            Store,
+            "Search time limit",
+        );
+        parser.refer(&mut options.time_management).add_option(
+            &["--time-management"],
+            Store,
+            "Stop the search early when another iteration is not worth it",
+        );
+        parser.refer(&mut options.aspiration_windows).add_option(
+            &["--aspiration-windows"],
+            Store,
+            "Enable aspiration windows",
+        );
+        parser.refer(&mut options.endgame_solver).add_option(
+            &["--endgame-solver"],
+            Store,
+            "Solve positions close to the end of the game with proof-number search",
+        );
+        parser.refer(&mut options.max_depth).add_option(
+            &["-d", "--depth"],
+            Store,
+            "Search depth limit",
+        );
+        parser.refer(&mut options.max_nodes).add_option(
+            &["-n", "--nodes"],
+            Store,
+            "Search node limit",
+        );
+        parser.refer(&mut options.hash_size).add_option(
+            &["--hash"],
+            Store,
+            "Size of the transposition table in megabytes",
+        );
+        parser.refer(&mut options.threads).add_option(
+            &["-j", "--threads"],
+            Store,
+            "Number of search threads",
+        );
+        parser
+            .refer(&mut options.selective_search.null_move_pruning)
+            .add_option(&["--null-move-pruning"], Store, "Enable null move pruning");
+        parser
+            .refer(&mut options.selective_search.late_move_reductions)
+            .add_option(
+                &["--late-move-reductions"],
+                Store,
+                "Enable late move reductions",
+            );
+        parser
+            .refer(&mut options.selective_search.futility_pruning)
+            .add_option(&["--futility-pruning"], Store, "Enable futility pruning");
         parser
-            .refer(&mut time_limit)
-            .add_option(&["-t", "--time"], Store, "Search time limit");
+            .refer(&mut options.selective_search.razoring)
+            .add_option(&["--razoring"], Store, "Enable razoring");
+        parser
+            .refer(&mut options.selective_search.threat_extensions)
+            .add_option(&["--threat-extensions"], Store, "Enable threat extensions");
+        parser.refer(&mut options.parameters_path).add_option(
+            &["-e", "--parameters"],
+            Store,
+            "Load the evaluation parameters from a JSON file",
+        );
+        parser.refer(&mut options.parameters_json).add_option(
+            &["--parameters-json"],
+            Store,
+            "Evaluation parameters as a JSON string",
+        );
+        parser.refer(&mut options.network_path).add_option(
+            &["--network"],
+            Store,
+            "Use the neural network evaluation with the weights from this file",
+        );
+        parser.refer(&mut options.info_lines).add_option(
+            &["--info-lines"],
+            Store,
+            "Print the search progress as machine readable info lines",
+        );
         parser.parse_args_or_exit();
     }
 
-    let mut player = Box::new(Algorithm::default());
-    player.set_time_limit(time_limit);
-    if test {
-        run_test(player);
+    let mut parameters = EvaluationParameters::default();
+    if !options.parameters_path.is_empty() {
+        parameters = EvaluationParameters::load(&options.parameters_path).unwrap_or_else(|e| {
+            panic!(
+                "Can't load the parameters {}: {}",
+                options.parameters_path, e
+            )
+        });
+    }
+    if !options.parameters_json.is_empty() {
+        parameters = EvaluationParameters::from_json(&options.parameters_json)
+            .unwrap_or_else(|e| panic!("Can't parse the parameters: {}", e));
+    }
+    if options.network_path.is_empty() {
+        let evaluator = StaticEvaluator::new(parameters.clone());
+        run(
+            Box::new(Algorithm::with_evaluator(evaluator)),
+            options,
+            &parameters,
+        );
+    } else {
+        let network = NeuralNetwork::load(&options.network_path)
+            .unwrap_or_else(|e| panic!("Can't load the network {}: {}", options.network_path, e));
+        let evaluator = NeuralEvaluator::new(Arc::new(network));
+        run(
+            Box::new(Algorithm::with_evaluator(evaluator)),
+            options,
+            &parameters,
+        );
+    }
+}
+
+fn run<E: Evaluator + 'static>(
+    mut player: Box<Algorithm<E>>,
+    options: Options,
+    parameters: &EvaluationParameters,
+) {
+    player.selective_search = options.selective_search;
+    if options.info_lines {
+        player.observers = vec![Box::new(InfoLinePrinter)];
+    }
+    if options.hash_size != DEFAULT_HASH_SIZE {
+        player.set_hash_size(options.hash_size);
+    }
+    player.set_time_limit(options.time_limit);
+    player.time_management = options.time_management;
+    player.aspiration_windows = options.aspiration_windows;
+    player.endgame_solver = options.endgame_solver;
+    player.limits.max_depth = options.max_depth;
+    player.limits.max_nodes = options.max_nodes;
+    player.set_threads(options.threads);
+    if options.test {
+        run_test(&mut player, parameters);
     } else {
-        println!("{}:{} {}", host, port, reservation);
-        let mut client = XmlClient::new(host, port, reservation, player);
+        println!("{}:{} {}", options.host, options.port, options.reservation);
+        let mut client = XmlClient::new(options.host, options.port, options.reservation, player);
         client.run();
     }
 }
@@ -30,7 +30,7 @@ impl XmlClient {
 
     pub fn run(&mut self) {
         print!("Connecting to {}:{}... ", self.host, self.port);
-        let stream = TcpStream::connect(&format!("{}:{}", self.host, self.port))
+        let stream = TcpStream::connect(format!("{}:{}", self.host, self.port))
             .expect("Could not connect to server");
         println!("Connected");
         Self::write_to(&stream, "<protocol>");
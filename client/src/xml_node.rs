@@ -165,12 +165,7 @@ impl XmlNode {
     }
 
     pub fn get_child(&self, name: &str) -> Option<&XmlNode> {
-        for child in &self.childs {
-            if child.name.as_str() == name {
-                return Some(child);
-            }
-        }
-        None
+        self.childs.iter().find(|child| child.name.as_str() == name)
     }
 
     pub fn get_attribute(&self, name: &str) -> Option<&String> {
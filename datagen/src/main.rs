@@ -0,0 +1,204 @@
+use argparse::{ArgumentParser, Store};
+use game_sdk::action::{Action, ActionList};
+use game_sdk::gamerules;
+use game_sdk::gamestate::{GameState, RED};
+use game_sdk::player::Player;
+use player::minimax::evaluation::EvaluationParameters;
+use player::minimax::limits::SearchLimits;
+use player::minimax::search::Searcher;
+use rand::{rngs::SmallRng, RngCore, SeedableRng};
+use std::fs::OpenOptions;
+use std::io::{BufWriter, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Instant;
+
+#[derive(Clone)]
+pub struct Settings {
+    games: usize,
+    depth: usize,
+    nodes: usize,
+    random_plies: u8,
+    hash_size: usize,
+    parameters: EvaluationParameters,
+}
+
+pub struct Record {
+    fen: String,
+    value: i16,
+    action: Action,
+}
+
+// Plays random actions for the first plies so that the games don't repeat
+fn random_opening(rng: &mut SmallRng, plies: u8) -> GameState {
+    let mut al = ActionList::default();
+    loop {
+        let mut state = GameState::random();
+        while state.ply < plies && !gamerules::is_game_over(&state) {
+            gamerules::get_legal_actions(&state, &mut al);
+            let action = al[rng.next_u64() as usize % al.size];
+            gamerules::do_action(&mut state, action);
+        }
+        if !gamerules::is_game_over(&state) {
+            return state;
+        }
+    }
+}
+
+fn play_game(searcher: &mut Searcher, state: &mut GameState) -> Vec<Record> {
+    let mut records = Vec::new();
+    while !gamerules::is_game_over(state) {
+        let action = searcher.search(state);
+        // The value of the search is relative to the player to move, store it for red
+        let value = if state.get_current_color() == RED {
+            searcher.best_value
+        } else {
+            -searcher.best_value
+        };
+        records.push(Record {
+            fen: state.to_fen(),
+            value,
+            action,
+        });
+        gamerules::do_action(state, action);
+    }
+    records
+}
+
+pub fn run_thread(
+    settings: Settings,
+    games_played: Arc<AtomicUsize>,
+    positions_written: Arc<AtomicUsize>,
+    output: Arc<Mutex<BufWriter<std::fs::File>>>,
+) -> JoinHandle<()> {
+    thread::spawn(move || {
+        let mut rng = SmallRng::from_entropy();
+        let mut searcher = Searcher::default();
+        searcher.observers.clear();
+        searcher.set_hash_size(settings.hash_size);
+        // Every position is searched with the same depth and node settings
+        searcher.endgame_solver = false;
+        searcher.limits = SearchLimits {
+            max_depth: settings.depth,
+            max_nodes: settings.nodes,
+            movetime: None,
+        };
+        searcher.evaluator.parameters = settings.parameters.clone();
+        while games_played.fetch_add(1, Ordering::Relaxed) < settings.games {
+            let mut state = random_opening(&mut rng, settings.random_plies);
+            // The games don't share anything, so that they don't depend on each other
+            searcher.reset();
+            let records = play_game(&mut searcher, &mut state);
+            let result = gamerules::game_result(&state);
+            let mut lines = String::new();
+            for record in records.iter() {
+                lines.push_str(&format!(
+                    "{};{};{};{}\n",
+                    record.fen,
+                    record.value,
+                    record.action.serialize(),
+                    result
+                ));
+            }
+            output.lock().unwrap().write_all(lines.as_bytes()).unwrap();
+            positions_written.fetch_add(records.len(), Ordering::Relaxed);
+        }
+    })
+}
+
+fn main() {
+    let mut path = "dataset.txt".to_string();
+    let mut games: usize = 1000;
+    let mut threads: usize = 1;
+    let mut depth: usize = 6;
+    let mut nodes: usize = usize::MAX;
+    let mut random_plies: u8 = 4;
+    let mut hash_size: usize = 16;
+    let mut parameters_path = "".to_string();
+
+    {
+        let mut parser = ArgumentParser::new();
+        parser
+            .refer(&mut path)
+            .add_option(&["-o", "--output"], Store, "Output file");
+        parser
+            .refer(&mut games)
+            .add_option(&["-g", "--games"], Store, "Number of games");
+        parser
+            .refer(&mut threads)
+            .add_option(&["-j", "--threads"], Store, "Number of threads");
+        parser
+            .refer(&mut depth)
+            .add_option(&["-d", "--depth"], Store, "Search depth");
+        parser
+            .refer(&mut nodes)
+            .add_option(&["-n", "--nodes"], Store, "Node limit per search");
+        parser.refer(&mut random_plies).add_option(
+            &["-r", "--random-plies"],
+            Store,
+            "Number of random plies at the start of each game",
+        );
+        parser.refer(&mut hash_size).add_option(
+            &["--hash"],
+            Store,
+            "Size of the transposition table of each thread in megabytes",
+        );
+        parser.refer(&mut parameters_path).add_option(
+            &["-e", "--parameters"],
+            Store,
+            "Load the evaluation parameters from a JSON file",
+        );
+        parser.parse_args_or_exit();
+    }
+
+    let parameters = if parameters_path.is_empty() {
+        EvaluationParameters::default()
+    } else {
+        EvaluationParameters::load(&parameters_path)
+            .unwrap_or_else(|e| panic!("Can't load the parameters {}: {}", parameters_path, e))
+    };
+    let settings = Settings {
+        games,
+        depth,
+        nodes,
+        random_plies,
+        hash_size,
+        parameters,
+    };
+    // Every line contains: FEN;value;action;result
+    // The value and the result are given from the perspective of red. The dataset is text
+    // like the one the tuner reads, see the README for why it isn't a binary format.
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .unwrap_or_else(|_| panic!("Can't open the output file: {}", path));
+    let output = Arc::new(Mutex::new(BufWriter::new(file)));
+    let games_played = Arc::new(AtomicUsize::new(0));
+    let positions_written = Arc::new(AtomicUsize::new(0));
+    let start_time = Instant::now();
+
+    let handles: Vec<JoinHandle<()>> = (0..threads.max(1))
+        .map(|_| {
+            run_thread(
+                settings.clone(),
+                Arc::clone(&games_played),
+                Arc::clone(&positions_written),
+                Arc::clone(&output),
+            )
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    output.lock().unwrap().flush().unwrap();
+    let positions = positions_written.load(Ordering::Relaxed);
+    println!(
+        "Wrote {} positions from {} games to {} in {}s",
+        positions,
+        games,
+        path,
+        start_time.elapsed().as_secs()
+    );
+}
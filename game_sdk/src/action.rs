@@ -31,6 +31,16 @@ impl Action {
         )
     }
 
+    #[inline(always)]
+    pub fn from_bits(bits: u16) -> Self {
+        Self(bits)
+    }
+
+    #[inline(always)]
+    pub fn bits(self) -> u16 {
+        self.0
+    }
+
     #[inline(always)]
     pub fn from(self) -> u16 {
         self.0 & FROM_MASK
@@ -118,7 +128,7 @@ impl UndoInfo {
 
     #[inline(always)]
     pub fn set_capture(&mut self, piece: u8, capture_info: u8) {
-        self.0 |= 0b1 | (piece as u8) << 1 | capture_info << 3;
+        self.0 |= 0b1 | piece << 1 | capture_info << 3;
     }
 
     #[inline(always)]
@@ -165,20 +175,16 @@ impl ActionList {
     }
 
     pub fn find_action(&self, action: Action) -> Option<usize> {
-        for i in 0..self.size {
-            if self.actions[i] == action {
-                return Some(i);
-            }
-        }
-        None
+        (0..self.size).find(|&i| self.actions[i] == action)
     }
 }
 
 impl Default for ActionList {
     fn default() -> Self {
-        #[allow(clippy::uninit_assumed_init)]
-        let actions = unsafe { std::mem::MaybeUninit::uninit().assume_init() };
-        Self { actions, size: 0 }
+        Self {
+            actions: [Action::NONE; MAX_ACTIONS],
+            size: 0,
+        }
     }
 }
 
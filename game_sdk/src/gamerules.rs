@@ -1,7 +1,7 @@
 use super::action::*;
 use super::bitboard::*;
 use super::gamestate::*;
-use super::hashing::ZOBRIST_KEYS;
+use super::hashing::{amber_key, SIDE_TO_MOVE_KEY, ZOBRIST_KEYS};
 use super::piece;
 
 #[rustfmt::skip]
@@ -14,25 +14,14 @@ pub const COCKLE_PATTERN: [u64; 128] = [512, 1024, 2048, 4096, 8192, 16384, 3276
 pub const GULL_PATTERN: [u64; 64] = [258, 517, 1034, 2068, 4136, 8272, 16544, 32832, 66049, 132354, 264708, 529416, 1058832, 2117664, 4235328, 8405120, 16908544, 33882624, 67765248, 135530496, 271060992, 542121984, 1084243968, 2151710720, 4328587264, 8673951744, 17347903488, 34695806976, 69391613952, 138783227904, 277566455808, 550837944320, 1108118339584, 2220531646464, 4441063292928, 8882126585856, 17764253171712, 35528506343424, 71057012686848, 141014513745920, 283678294933504, 568456101494784, 1136912202989568, 2273824405979136, 4547648811958272, 9095297623916544, 18190595247833088, 36099715518955520, 72621643502977024, 145524761982664704, 291049523965329408, 582099047930658816, 1164198095861317632, 2328396191722635264, 4656792383445270528, 9241527172852613120, 144396663052566528, 360850920143060992, 721701840286121984, 1443403680572243968, 2886807361144487936, 5773614722288975872, 11547229444577951744, 4647714815446351872];
 
 pub fn is_game_over(state: &GameState) -> bool {
-    state.ply >= 59 || ((state.ambers[0] > 1 || state.ambers[1] > 1) && state.ply % 2 == 0)
+    state.ply >= 59 || ((state.ambers[0] > 1 || state.ambers[1] > 1) && state.ply.is_multiple_of(2))
 }
 
 pub fn game_result(state: &GameState) -> i16 {
     // only works when is_game_over returns true
     #[allow(clippy::comparison_chain)]
     if state.ambers[0] == state.ambers[1] {
-        let blue_light_figures = state.occupied[BLUE] & !state.board[BLUE][piece::SEAL as usize];
-        let red_light_figures = state.occupied[RED] & !state.board[RED][piece::SEAL as usize];
-        for i in 0..8 {
-            let red = (FINISH_LINES[RED] >> i & red_light_figures).count_ones();
-            let blue = (FINISH_LINES[BLUE] << i & blue_light_figures).count_ones();
-            if red > blue {
-                return 1;
-            } else if blue > red {
-                return -1;
-            }
-        }
-        0
+        tie_break(state)
     } else if state.ambers[0] > state.ambers[1] {
         1
     } else {
@@ -40,6 +29,24 @@ pub fn game_result(state: &GameState) -> i16 {
     }
 }
 
+// Decides the game if both players have the same number of ambers. The player with more
+// light pieces on the column closest to the finish line wins, starting at the finish line.
+pub fn tie_break(state: &GameState) -> i16 {
+    let blue_light_figures = state.occupied[BLUE] & !state.board[BLUE][piece::SEAL as usize];
+    let red_light_figures = state.occupied[RED] & !state.board[RED][piece::SEAL as usize];
+    for i in 0..8 {
+        let red = (FINISH_LINES[RED] >> i & red_light_figures).count_ones();
+        let blue = (FINISH_LINES[BLUE] << i & blue_light_figures).count_ones();
+        #[allow(clippy::comparison_chain)]
+        if red > blue {
+            return 1;
+        } else if blue > red {
+            return -1;
+        }
+    }
+    0
+}
+
 pub fn do_action(state: &mut GameState, action: Action) {
     let color = state.get_current_color();
     let mut undo_info = UndoInfo::default();
@@ -50,6 +57,7 @@ pub fn do_action(state: &mut GameState, action: Action) {
     let changed_fields = to_bit | from_bit;
     let piece = action.piece() as usize;
     let is_piece_stacked = state.stacked & from_bit > 0;
+    let ambers = state.ambers[color];
     if to_bit & state.occupied[other_color] > 0 {
         // Move is a capture
         let changed_fields_that_are_stacked = changed_fields & state.stacked;
@@ -90,10 +98,10 @@ pub fn do_action(state: &mut GameState, action: Action) {
             capture_info |= MOVED_PIECE_WAS_STACKED;
         }
         // Remove the opponents piece
-        for piece in 0..4 {
-            if state.board[other_color][piece] & to_bit > 0 {
+        for (piece, bitboard) in state.board[other_color].iter_mut().enumerate() {
+            if *bitboard & to_bit > 0 {
                 state.hash ^= ZOBRIST_KEYS[other_color][piece][action.to() as usize];
-                state.board[other_color][piece] &= mask; // Remove the opponents piece
+                *bitboard &= mask; // Remove the opponents piece
                 undo_info.set_capture(piece as u8, capture_info);
                 break;
             }
@@ -115,7 +123,7 @@ pub fn do_action(state: &mut GameState, action: Action) {
         }
     }
     // Check whether a piece reached the finish line
-    if piece as usize != piece::SEAL as usize && to_bit & FINISH_LINES[color] > 0 {
+    if piece != piece::SEAL as usize && to_bit & FINISH_LINES[color] > 0 {
         if is_piece_stacked {
             undo_info.set_finish_line_info(MOVED_PIECE_WAS_STACKED);
         }
@@ -131,10 +139,29 @@ pub fn do_action(state: &mut GameState, action: Action) {
         }
         state.stacked &= mask;
     }
+    if state.ambers[color] != ambers {
+        state.hash ^= amber_key(color, ambers) ^ amber_key(color, state.ambers[color]);
+    }
+    state.hash ^= SIDE_TO_MOVE_KEY;
     state.undo[state.ply as usize] = undo_info;
     state.ply += 1;
 }
 
+// Passes the turn to the other player. This is not a legal action in the game, it is
+// only used by the search.
+pub fn do_null_action(state: &mut GameState) {
+    let mut undo_info = UndoInfo::default();
+    undo_info.set_hash(state.hash);
+    state.hash ^= SIDE_TO_MOVE_KEY;
+    state.undo[state.ply as usize] = undo_info;
+    state.ply += 1;
+}
+
+pub fn undo_null_action(state: &mut GameState) {
+    state.ply -= 1;
+    state.hash = state.undo[state.ply as usize].get_hash();
+}
+
 pub fn undo_action(state: &mut GameState, action: Action) {
     state.ply -= 1;
     let color = state.get_current_color();
@@ -176,10 +203,69 @@ pub fn undo_action(state: &mut GameState, action: Action) {
 pub fn get_legal_actions(state: &GameState, al: &mut ActionList) {
     al.clear();
     let color = state.get_current_color();
-    color_dependent_pieces(state, al, color, piece::COCKLE, &COCKLE_PATTERN);
-    color_dependent_pieces(state, al, color, piece::STARFISH, &STARFISH_PATTERN);
-    color_independent_pieces(state, al, color, piece::SEAL, &SEAL_PATTERN);
-    color_independent_pieces(state, al, color, piece::GULL, &GULL_PATTERN);
+    let targets = !state.occupied[color];
+    color_dependent_pieces(state, al, color, piece::COCKLE, &COCKLE_PATTERN, targets);
+    color_dependent_pieces(
+        state,
+        al,
+        color,
+        piece::STARFISH,
+        &STARFISH_PATTERN,
+        targets,
+    );
+    color_independent_pieces(state, al, color, piece::SEAL, &SEAL_PATTERN, targets);
+    color_independent_pieces(state, al, color, piece::GULL, &GULL_PATTERN, targets);
+}
+
+// Checks whether get_legal_actions would generate the action, including its flags. Used
+// to try actions that were stored in an earlier position without generating all actions.
+pub fn is_legal_action(state: &GameState, action: Action) -> bool {
+    let color = state.get_current_color();
+    let from = action.from() as usize;
+    let to_bit = 1 << action.to();
+    let from_bit = 1 << from;
+    let piece = action.piece() as u8;
+    if state.board[color][piece as usize] & from_bit == 0 || state.occupied[color] & to_bit > 0 {
+        return false;
+    }
+    let destinations = match piece {
+        piece::COCKLE => COCKLE_PATTERN[from | color << 6],
+        piece::GULL => GULL_PATTERN[from],
+        piece::STARFISH => STARFISH_PATTERN[from | color << 6],
+        _ => SEAL_PATTERN[from],
+    };
+    if destinations & to_bit == 0 {
+        return false;
+    }
+    let is_capture = to_bit & state.occupied[color ^ 1] > 0;
+    let is_amber_capture = is_capture && (from_bit | to_bit) & state.stacked > 0;
+    action
+        == Action::new(
+            from as u16,
+            action.to(),
+            piece,
+            is_capture,
+            is_amber_capture,
+        )
+}
+
+// Only generates captures and actions that move a light piece onto the finish line
+pub fn get_tactical_actions(state: &GameState, al: &mut ActionList) {
+    al.clear();
+    let color = state.get_current_color();
+    let captures = state.occupied[color ^ 1];
+    let targets = (captures | FINISH_LINES[color]) & !state.occupied[color];
+    color_dependent_pieces(state, al, color, piece::COCKLE, &COCKLE_PATTERN, targets);
+    color_dependent_pieces(
+        state,
+        al,
+        color,
+        piece::STARFISH,
+        &STARFISH_PATTERN,
+        targets,
+    );
+    color_independent_pieces(state, al, color, piece::SEAL, &SEAL_PATTERN, captures);
+    color_independent_pieces(state, al, color, piece::GULL, &GULL_PATTERN, targets);
 }
 
 #[inline(always)]
@@ -215,6 +301,7 @@ fn color_dependent_pieces(
     color: usize,
     piece: u8,
     pattern: &[u64; 128],
+    targets: u64,
 ) {
     let other_color_occupied = state.occupied[color ^ 1];
     let mut pieces = state.board[color][piece as usize];
@@ -228,7 +315,7 @@ fn color_dependent_pieces(
             from as u16,
             from_bit,
             state.stacked,
-            pattern[from as usize | color << 6] & !state.occupied[color],
+            pattern[from as usize | color << 6] & targets,
             other_color_occupied,
         );
     }
@@ -241,6 +328,7 @@ fn color_independent_pieces(
     color: usize,
     piece: u8,
     pattern: &[u64; 64],
+    targets: u64,
 ) {
     let other_color_occupied = state.occupied[color ^ 1];
     let mut pieces = state.board[color][piece as usize];
@@ -254,7 +342,7 @@ fn color_independent_pieces(
             from as u16,
             from_bit,
             state.stacked,
-            pattern[from as usize] & !state.occupied[color],
+            pattern[from as usize] & targets,
             other_color_occupied,
         );
     }
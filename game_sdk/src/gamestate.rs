@@ -1,6 +1,6 @@
 use super::action::UndoInfo;
 use super::bitboard::format_bitboard;
-use super::hashing::ZOBRIST_KEYS;
+use super::hashing::{amber_key, SIDE_TO_MOVE_KEY, ZOBRIST_KEYS};
 use super::piece;
 use rand::{rngs::SmallRng, RngCore, SeedableRng};
 use std::fmt::{Display, Formatter, Result};
@@ -61,19 +61,23 @@ impl GameState {
         let mut hash: u64 = 0;
         for color in COLORS {
             for piece in piece::PIECES {
-                let mut pieces = self.board[color as usize][piece as usize];
+                let mut pieces = self.board[color][piece as usize];
                 while pieces > 0 {
                     let position = pieces.trailing_zeros();
                     pieces ^= 1 << position;
-                    hash ^= ZOBRIST_KEYS[color as usize][piece as usize][position as usize];
+                    hash ^= ZOBRIST_KEYS[color][piece as usize][position as usize];
                 }
             }
-            let mut stacked = self.stacked & self.occupied[color as usize];
+            let mut stacked = self.stacked & self.occupied[color];
             while stacked > 0 {
                 let position = stacked.trailing_zeros();
                 stacked ^= 1 << position;
-                hash ^= ZOBRIST_KEYS[color as usize][4][position as usize];
+                hash ^= ZOBRIST_KEYS[color][4][position as usize];
             }
+            hash ^= amber_key(color, self.ambers[color]);
+        }
+        if self.get_current_color() == BLUE {
+            hash ^= SIDE_TO_MOVE_KEY;
         }
         self.hash = hash;
     }
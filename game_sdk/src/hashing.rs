@@ -1,2 +1,24 @@
 #[rustfmt::skip]
 pub const ZOBRIST_KEYS: [[[u64; 64]; 5]; 2] = [[[10042630031591754363, 11239981557269688196, 11122851966867898219, 12658245339223026419, 12550950364491074770, 13198703990969171940, 10601433503804624657, 13833044338088693409, 11590021817564597481, 12926282469008527366, 13305691125822268952, 10914960784952162518, 11452714177531158476, 13440922646345727573, 12351450122964363103, 11601735580691918291, 9404955642506393897, 13762055833304596814, 13690235418954768000, 10555565173986380768, 13219685551205646528, 12296463471728983023, 9935058038301970209, 13713861875106439599, 9862059348167190912, 10894829248123109618, 12822759277776096664, 10148663448876688331, 11426221087696077946, 12019544211943282723, 12174044147795397116, 13306241073344633056, 10579780393185611050, 9921503543595986172, 12749252517588715726, 13621480339826291442, 10444614841726058547, 10395468792293311705, 12386639011859679423, 12672792497637053458, 11776575888423944023, 12980277635680311818, 9433672070122162652, 13162490202046418402, 9837337576218229709, 9422811958909730937, 13000608122825001344, 9255497635914318227, 10666467803150052170, 12894048695434295465, 9903846210298141534, 12109127473520139006, 12385891821074630516, 12997720695420772066, 9725490685474990178, 10766212263640223181, 11419742783890246332, 13433584128502984666, 13305204024762736264, 11036965779864522154, 13695918699643335753, 11072476997923995563, 9773193541467190258, 11075299079447363325], [11579704004791357881, 13823801514111975797, 13037120875376376357, 13575984384298252415, 13306208167769711737, 13578963552037104799, 10962296932291357800, 11424845161067071813, 10467458872642536904, 12588162636516790109, 13276100585904316682, 13668041094023489091, 9671280029064725956, 10684146538574281263, 11477781065968149737, 11913715760357097043, 11870013987657632546, 10222487943384593206, 10351036757469486514, 10721668626621501529, 11331764677216116094, 10111302143283464800, 9648141286136957731, 10400834577206333262, 10960489732025805861, 12542869930993927321, 12622783846131236456, 13231147836875377433, 13493182828771272721, 13782105490116484594, 13215113665799682753, 10209349426461665840, 11882534348250054218, 13624403949555814264, 10709389837166488473, 9546411633472828788, 11869049723031306915, 12524411220557535288, 11681023695066320062, 12238607113618799092, 12589280065833189694, 13158880891969306346, 13679800284031328638, 10194738889450614155, 11312961832000287349, 12331703811723100770, 11667943243043283641, 11404692966655719913, 12564670210337459500, 12434525622445448744, 9691636463629677416, 12680394125105499655, 10118755880310875097, 12075466467287453118, 10052013946293243454, 11252857593031296454, 11026677824647704581, 12187217329152213031, 13572377595373192033, 9989441737707457691, 9649637021847698651, 13199030533390803319, 9399662792186257758, 10894440376906298697], [9550697216485975281, 9688590448045908080, 12342171596639320516, 13705205336116928542, 13495122637304288910, 11580409478958587444, 13787960596178523158, 10219912156217170474, 12105340821408091537, 13126110758483688812, 10449985260187107376, 12713039663031342455, 9886329677241736614, 12989530171648887480, 13761876990902272032, 9680586088790545067, 10648203707199681477, 11769370586047501324, 13665741583243705274, 12257919272316847689, 9753663101579293121, 12244852546785018341, 11588981670165099156, 10863714844747958577, 13745659805836209293, 9445841503203527692, 12429116438690464838, 12882031736150970535, 13700683308959984378, 10898498296017030748, 10745028750038442977, 9672458915438700844, 13703981249885270221, 9747859617947147937, 10814415275504845426, 12262624773642542446, 13508886676309532512, 9875952530231507465, 9451365395271686030, 12621205144724542723, 9878411714071268994, 10418426561620555030, 9503286982488866035, 9438394236915695055, 12574458644005915315, 11580547116679520269, 12955368485062557624, 9832662531678911479, 11234914902837781511, 11452875392904181829, 9584770336950001752, 9863361141568409617, 13705331354024997852, 9244933766550578940, 12128793756574834320, 11580539070525848868, 10519737732652279711, 9660813260012307444, 13754397500859041483, 10726064526835885243, 11506295623685601722, 11799333718061470446, 11632210249335538687, 11523699835127880270], [12051887561724213790, 10357685262596075237, 10143038145250963259, 10080980684272234789, 13218842716039783007, 13470221551873385639, 13445032699065982130, 10269609602127485601, 11230998832365115249, 12273090428210094636, 10474114405988038895, 13034567492001575822, 11999196268325140767, 9656286981725015950, 12452684653597922953, 13589250642770337736, 11019744692332463708, 13111373052639511518, 10089477953558443635, 10843207698266230473, 12266613117433032025, 10342567032961454150, 10019746744374167706, 11521742624900995154, 9262033419750757723, 11267437233742666670, 11395141205571092811, 12995828468662462426, 9720080154037000508, 12868907464472867552, 9762010086358346066, 11086510004332624877, 10124892846405866116, 13800557927449268585, 12097284132634132137, 10889907501662361327, 9303234349329302423, 13772632452308154873, 11203826653763441508, 12672966906374613680, 12800975636540475875, 12881622693442352457, 13507171562576104700, 9868861570817938257, 13299969386640550162, 12319313000685849395, 9638889451133202821, 11086150772693755787, 13547925099413110129, 11399322641698743767, 12358133046268481718, 12021168423251586873, 11598568649734753416, 10191137079068435441, 12174370967404348679, 12184240127222747559, 10507653902585334977, 10607372966363762017, 11757468926836234075, 11214574628237520188, 12230133840336996392, 11766517489143826788, 11427072273288086002, 10735782563995821396], [11123499905884263622, 13724669995020303268, 11323339420464199574, 12157051689296935247, 11703955208767321732, 13683631627228625690, 10987869824560167630, 11265187026233041770, 13282315286966847499, 11937852926206823418, 11080368598283727919, 12636442922675729935, 9559851411804608747, 11786747965095349765, 10002439354020181286, 12598797555633998945, 9255234382976260769, 11723093176380928575, 12957906008887366769, 10010794433212761131, 10926852522194547544, 9396554409021446627, 9715167094022532571, 9621248411400924390, 11594509249366514043, 11241754000605122302, 10443826670244506066, 10439711588102087670, 9970264883835890571, 13730635057721147613, 11802532489414494006, 11263373298325454013, 12302758294676956643, 12838560356732568280, 9741939610848256086, 13669366424011770472, 9407722464266445282, 10386820763337676976, 12780976664870578843, 9526845740592917089, 12769906003575165286, 9737911918609249695, 12064030182741338816, 10097053008445790493, 11901804086724539348, 12904188688481344132, 11922503683222496359, 10919211923077590618, 12082030810125140488, 10811187045579143156, 10905151620278724926, 12104158952674540996, 13484611686188974886, 10409046830654883431, 9442280704064847987, 13630595686391825551, 12735622233884379903, 10188658567181875760, 10558595872820963780, 13493610730074489836, 11866237961319381343, 9430402031946282066, 10814204278180789671, 12046516281617153048]], [[12660044768113726798, 12690849508460468641, 9891075308213690776, 9535095818348445459, 12948393220403092849, 12425942441275416822, 12556246217582836444, 12656447213161336925, 12906767708130442383, 12871863390591313064, 12747610193986493617, 11470972392090741071, 9848019208655025137, 13613452053360629648, 12439827217987972214, 9933383657742105948, 12473080499832330115, 12436814465788171129, 13772142615900738407, 12618332963297991921, 13005160972409243877, 11088354894007682493, 13658514700775153756, 11184969263578269081, 13825156218759390791, 13464065640221286313, 12449759247747551112, 12831506507756596526, 11650465255669742481, 9803624959900030536, 13418781888605140672, 13248146400985908712, 12382339387931060313, 13172624614308164357, 11911637207742825890, 12184465409083406535, 13571337715322444612, 11453675335751133896, 11240703315029467476, 10817087898123048294, 11117928754570910191, 11924753074100378121, 9484751265631090451, 13822507880739576003, 9570737565107558784, 10980551622190998305, 9729940949035958101, 10712403422559547606, 9732943574925508193, 12234029154271551642, 11097237377177674284, 10261246552975324610, 9977560973441444937, 9436381338120954471, 11053472974938611120, 13688778286310420564, 12329916997362453823, 11391486479178372627, 13716845561735047423, 10987460883914782273, 11416616936056425796, 9690211589637497681, 13465418713348979850, 10847298845939025845], [12486657838668717983, 11273998997543255441, 11924895913952017286, 11714102020186135274, 10319687442069674711, 12561375823912209216, 10361774246202305037, 12911565212941051340, 9785173639609578113, 9499660432157597909, 9628744293020088362, 10045382751816986108, 10852880541962266754, 11079780800972900300, 12589979951213169387, 12469223094747388371, 10707984045513203512, 11949790687508190030, 11506969316923868918, 10171075535862399261, 10506643975373994204, 12543927112635652457, 12529498730340804494, 12751122554267990868, 11623582592935601284, 12617107182065909877, 12983542784956555060, 9826210584710680708, 12040065873747481681, 10357493486727608814, 10722930452232527370, 13096361626305944930, 12999519653083444713, 9386411282633580886, 13156895257588715346, 13564341769634445372, 13821522093796279201, 13032460243393477869, 10147722209936952104, 10495781259314548339, 9451104616342466081, 13662027738928586645, 10373225112149807668, 10356378791014180657, 12763516139416387788, 10486187238820905934, 11779386870037181887, 11098393308140670066, 9474016308095306424, 10103394063900372855, 12969838733731483370, 12816813702823644940, 9860305413279341722, 12660235931397291353, 13807294127707786845, 12421861662937584911, 12878086015288502204, 9751041049404614806, 9643487528766336192, 9592533913698954587, 11298272145660532583, 11225005679103129115, 11696829812008577842, 12847529541230446301], [9442432449206980009, 9555981735614744580, 10665598973625992798, 11934490934521527803, 10946064600013627723, 13203148246563679628, 9827053306529314885, 12846802450654769061, 12406737412598012905, 13599919758186947627, 10462348713614164593, 10058813680536765482, 11986929166116287720, 12644097474129456371, 12838031356768789250, 10382046591519015430, 9245750983048114541, 11669924951752574995, 10688898785880192837, 12141581509643379989, 12417370012492565109, 9629209039892216108, 11685877465882182236, 9250084022152240944, 13746756085023714735, 10437149465114859187, 13452795194875990705, 11539377009635975448, 10301383091589042479, 9600839172829257985, 9309409705706639876, 12752367888492891213, 11389600146853699745, 11321903197287650265, 10521706986345052228, 10960286408335458613, 12400618875045127946, 12486800379080978076, 13511578305562308106, 10485594791556245857, 11464530214564947421, 12882145621815834661, 11166149716666702657, 12829307983659161057, 11930210581517837633, 12373970889969143530, 11993301595262251645, 10262965179194272486, 10351717935236888439, 11463110372520948154, 12807141796723008375, 11070792142871706915, 9427610658510905757, 10919732378504316355, 12706636053428647640, 10899047151768575843, 12287778916183195001, 9631215855797607574, 9794614804025192750, 11526080357398994690, 11304263823284923522, 12009198941160342993, 10297801943515485975, 9556512504738512509], [13743182043231693536, 10015256575498401548, 13649915940553996693, 12239285303204226021, 12911155646452144180, 12723370567561256282, 9879493646507880433, 12058151556740435728, 13832470388889463537, 11719679229686063421, 9244927547716754975, 13575491906515813335, 10597217242292483677, 12991098001617863274, 10915348086204470946, 10006281238281817224, 9839322894262288134, 12616451087621082092, 11262257316411276290, 13527046510770234394, 10794746945534125309, 11914454032767928594, 12802808721782623882, 11949749100244741027, 13033739453837354847, 13280081018479159098, 11253463419706587978, 13796167037054959983, 11420901335038617346, 11814580322420719724, 10027792361155444333, 12859965780826344781, 9648428616424229660, 10066637160455099127, 13222336933019285793, 12014937324769842386, 9668977252556621040, 10338923113987979565, 12255552973253560133, 10295511226602237609, 10099328029014719078, 11507104621158448331, 11261918551117665031, 10441944400455244258, 11573762803359519900, 9580145795206260241, 13387694506817688416, 12152915450557891659, 10633280312540370093, 10193611374115672407, 13686020156657383791, 10737794922873638128, 12393010396839249629, 11548338076063783251, 11569736415832478117, 13484186590366804577, 12554611206144923995, 10962544956152427526, 12435155370754307037, 11498450255834961973, 13518961619316357542, 11257865654636052256, 10273773596075076150, 11220023350983238509], [10183610862979745375, 11599745186698637116, 12439511239396254114, 10089177189844886560, 12319037142926146722, 11176563548688235886, 10209175978250167026, 9745983126181057095, 10627007717761828080, 9587866867933642821, 11522209296716681238, 12057328620629510219, 9263565337487354476, 10851971939821626015, 13205873718367436579, 12990393330470100151, 10314328635991773388, 10441993273088244046, 10716968641979309201, 10279616258754616583, 9731005993932066870, 10739811897712647024, 10573527317505714897, 9252635250406596803, 11534517186793503783, 10884116068095103855, 12734794716717460463, 9551931940852849305, 12903434790517398425, 11833728456001896199, 13178285678323187950, 12506667056733641817, 10934709164577074641, 12312582331576246911, 13325725594645023974, 12297803602652471802, 9872469592485396500, 10202935850138143314, 10883723921989070196, 11802595379745810778, 10838203955787706740, 10683200527236089435, 10646211803650866970, 10482750758460449848, 10894323730548178019, 9772903617783551353, 11411543778461812272, 10664750299526983480, 12877081973701386229, 12077881142163403304, 10583565352227347532, 11175338573718249031, 10557901649742821325, 13193463569185090377, 12595151576608063858, 10036582751738341757, 13291058180228112117, 11715675679578979616, 11529171162614099697, 12793980122194621394, 10846105333657733315, 12856769945586061251, 11409578952944505292, 13424173492668258912]]];
+// Keys for the number of ambers of each player, more than three ambers share the last key
+pub const AMBER_KEYS: [[u64; 4]; 2] = [
+    [
+        9753000221886482484,
+        10863243687414105020,
+        11801504061049214866,
+        9939523640827786373,
+    ],
+    [
+        12663661595096781807,
+        11644138894720735931,
+        10976705849217134004,
+        11610056858695893096,
+    ],
+];
+// Is part of the hash if it is blues turn
+pub const SIDE_TO_MOVE_KEY: u64 = 5329452988053889035;
+
+#[inline(always)]
+pub fn amber_key(color: usize, ambers: u8) -> u64 {
+    AMBER_KEYS[color][(ambers as usize).min(3)]
+}
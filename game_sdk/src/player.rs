@@ -7,4 +7,6 @@ pub trait Player {
     fn reset(&mut self) {}
 
     fn set_time_limit(&mut self, _: u64) {}
+
+    fn set_threads(&mut self, _: usize) {}
 }
@@ -1,6 +1,6 @@
-use super::action::{ActionList, ActionListStack};
+use super::action::{Action, ActionList, ActionListStack};
 use super::gamerules;
-use super::gamestate::GameState;
+use super::gamestate::{GameState, COLORS};
 use rand::{rngs::SmallRng, RngCore, SeedableRng};
 use std::time::Instant;
 /*
@@ -48,6 +48,27 @@ pub fn test_undo() {
     }
 }
 
+#[test]
+pub fn test_null_action() {
+    let mut rng = SmallRng::from_entropy();
+    let mut al = ActionList::default();
+    for _ in 0..100 {
+        let mut state = GameState::random();
+        while !gamerules::is_game_over(&state) {
+            let before = state.clone();
+            gamerules::do_null_action(&mut state);
+            assert_ne!(state.get_current_color(), before.get_current_color());
+            assert_ne!(state.hash, before.hash);
+            gamerules::undo_null_action(&mut state);
+            assert_eq!(state.ply, before.ply);
+            assert_eq!(state.hash, before.hash);
+            gamerules::get_legal_actions(&state, &mut al);
+            let action = al[rng.next_u64() as usize % al.size];
+            gamerules::do_action(&mut state, action);
+        }
+    }
+}
+
 #[test]
 pub fn test_hashing() {
     let mut rng = SmallRng::from_entropy();
@@ -72,6 +93,74 @@ pub fn test_hashing() {
             assert_eq!(state.hash, hash);
         }
     }
+
+    // Positions that only differ in the ambers or the player to move have different hashes
+    for _ in 0..100 {
+        let state = GameState::random();
+        for color in COLORS {
+            let mut other = state.clone();
+            other.ambers[color] += 1;
+            other.recalculate_hash();
+            assert_ne!(other.hash, state.hash);
+            assert!(other != state);
+        }
+        let mut other = state.clone();
+        other.ply += 1;
+        other.recalculate_hash();
+        assert_ne!(other.hash, state.hash);
+        assert!(other != state);
+    }
+}
+
+#[test]
+pub fn test_tactical_move_generation() {
+    let mut rng = SmallRng::from_entropy();
+    let mut al = ActionList::default();
+    let mut tactical_al = ActionList::default();
+    for _ in 0..1_000 {
+        let mut state = GameState::random();
+        while !gamerules::is_game_over(&state) {
+            let color = state.get_current_color();
+            gamerules::get_legal_actions(&state, &mut al);
+            gamerules::get_tactical_actions(&state, &mut tactical_al);
+            let mut tactical_actions = 0;
+            for i in 0..al.size {
+                let action = al[i];
+                if action.is_capture() || action.is_promotion(color) {
+                    tactical_actions += 1;
+                    assert!(tactical_al.find_action(action).is_some());
+                }
+            }
+            assert_eq!(tactical_al.size, tactical_actions);
+            let action = al[rng.next_u64() as usize % al.size];
+            gamerules::do_action(&mut state, action);
+        }
+    }
+}
+
+#[test]
+pub fn test_legal_action_check() {
+    let mut rng = SmallRng::from_entropy();
+    let mut al = ActionList::default();
+    for _ in 0..3 {
+        let mut state = GameState::random();
+        while !gamerules::is_game_over(&state) {
+            gamerules::get_legal_actions(&state, &mut al);
+            let mut is_legal = vec![false; 1 << 16];
+            for i in 0..al.size {
+                is_legal[al[i].bits() as usize] = true;
+            }
+            for bits in 0..=u16::MAX {
+                let action = Action::from_bits(bits);
+                assert_eq!(
+                    gamerules::is_legal_action(&state, action),
+                    is_legal[bits as usize]
+                );
+            }
+            let action = al[rng.next_u64() as usize % al.size];
+            gamerules::do_action(&mut state, action);
+        }
+    }
 }
 
 #[test]
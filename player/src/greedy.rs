@@ -1,29 +1,46 @@
-use super::minimax::evaluation::static_evaluation;
+use super::minimax::evaluator::{Evaluator, StaticEvaluator};
 use game_sdk::action::{Action, ActionList};
 use game_sdk::gamerules;
 use game_sdk::gamestate::{GameState, BLUE, RED};
 use game_sdk::player::Player;
 
-#[derive(Default)]
-pub struct GreedyPlayer {
+pub struct GreedyPlayer<E: Evaluator = StaticEvaluator> {
     al: ActionList,
+    pub evaluator: E,
 }
 
-impl GreedyPlayer {
+impl Default for GreedyPlayer {
+    fn default() -> Self {
+        Self::with_evaluator(StaticEvaluator::default())
+    }
+}
+
+impl<E: Evaluator> GreedyPlayer<E> {
+    pub fn with_evaluator(evaluator: E) -> Self {
+        Self {
+            al: ActionList::default(),
+            evaluator,
+        }
+    }
+
     pub fn get_action(&mut self, state: &GameState) -> Action {
         let mut state = state.clone();
         gamerules::get_legal_actions(&state, &mut self.al);
+        // The evaluation is from the perspective of red
         let color: i16 = match state.get_current_color() {
-            RED => -1,
-            BLUE => 1,
+            RED => 1,
+            BLUE => -1,
             _ => panic!(),
         };
+        self.evaluator.reset(&state);
         let mut best_action = Action::NONE;
-        let mut best_value = std::i16::MIN;
+        let mut best_value = i16::MIN;
         for i in 0..self.al.size {
             let action = self.al[i];
             gamerules::do_action(&mut state, action);
-            let value = static_evaluation(&state) * color;
+            self.evaluator.push(&state, action);
+            let value = self.evaluator.evaluate(&state) * color;
+            self.evaluator.pop();
             gamerules::undo_action(&mut state, action);
             if value > best_value {
                 best_value = value;
@@ -34,7 +51,7 @@ impl GreedyPlayer {
     }
 }
 
-impl Player for GreedyPlayer {
+impl<E: Evaluator> Player for GreedyPlayer<E> {
     fn on_move_request(&mut self, state: &GameState) -> Action {
         self.get_action(state)
     }
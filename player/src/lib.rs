@@ -1,3 +1,7 @@
 pub mod greedy;
+pub mod mcts;
 pub mod minimax;
 pub mod random;
+
+#[cfg(test)]
+pub mod tests;
@@ -0,0 +1,338 @@
+use super::greedy::GreedyPlayer;
+use super::minimax::evaluation::{static_evaluation, EvaluationParameters};
+use super::minimax::search_info::{SearchInfo, SearchObserver};
+use game_sdk::action::{Action, ActionList};
+use game_sdk::gamerules;
+use game_sdk::gamestate::{GameState, RED};
+use game_sdk::player::Player;
+use rand::{rngs::SmallRng, RngCore, SeedableRng};
+use std::time::Instant;
+
+pub const MAX_NODES: usize = 4_000_000;
+// Every n-th action of a greedy rollout is random on average, so that the rollouts differ
+pub const GREEDY_ROLLOUT_RANDOMNESS: u64 = 4;
+// Scale of the logistic function that converts an evaluation into a winning probability
+pub const EVALUATION_SCALE: f32 = 100.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RolloutPolicy {
+    // Plays uniformly random actions until the game is over
+    Random,
+    // Plays the action with the best static evaluation until the game is over
+    Greedy,
+    // Uses the static evaluation of the leaf instead of playing the game to the end
+    Evaluation,
+}
+
+#[derive(Clone, Copy)]
+struct Node {
+    action: Action,
+    visits: u32,
+    // Sum of the rewards for the player that did the action leading to this node
+    reward: f32,
+    children_start: usize,
+    children_count: u16,
+    is_expanded: bool,
+}
+
+impl Node {
+    fn new(action: Action) -> Self {
+        Self {
+            action,
+            visits: 0,
+            reward: 0.0,
+            children_start: 0,
+            children_count: 0,
+            is_expanded: false,
+        }
+    }
+
+    fn children(&self) -> std::ops::Range<usize> {
+        self.children_start..self.children_start + self.children_count as usize
+    }
+}
+
+pub struct MctsPlayer {
+    pub exploration_constant: f32,
+    pub rollout_policy: RolloutPolicy,
+    pub reuse_tree: bool,
+    pub evaluation_parameters: EvaluationParameters,
+    pub time_limit: u128,
+    pub iterations: usize,
+    // Are told about the result of every search, e.g. to print it
+    pub observers: Vec<Box<dyn SearchObserver>>,
+    nodes: Vec<Node>,
+    root_state: Option<GameState>,
+    greedy_player: GreedyPlayer,
+    rng: SmallRng,
+    al: ActionList,
+}
+
+impl Default for MctsPlayer {
+    fn default() -> Self {
+        Self {
+            exploration_constant: std::f32::consts::SQRT_2,
+            rollout_policy: RolloutPolicy::Random,
+            reuse_tree: true,
+            evaluation_parameters: EvaluationParameters::default(),
+            time_limit: 1970,
+            iterations: 0,
+            observers: Vec::new(),
+            nodes: Vec::new(),
+            root_state: None,
+            greedy_player: GreedyPlayer::default(),
+            rng: SmallRng::from_entropy(),
+            al: ActionList::default(),
+        }
+    }
+}
+
+impl Player for MctsPlayer {
+    fn on_move_request(&mut self, state: &GameState) -> Action {
+        self.search(state)
+    }
+
+    fn set_time_limit(&mut self, time_limit: u64) {
+        self.time_limit = time_limit as u128
+    }
+
+    fn reset(&mut self) {
+        self.nodes.clear();
+        self.root_state = None;
+    }
+}
+
+impl MctsPlayer {
+    pub fn search(&mut self, state: &GameState) -> Action {
+        let start_time = Instant::now();
+        for observer in self.observers.iter_mut() {
+            observer.on_search_start(state);
+        }
+        if !(self.reuse_tree && self.reuse_subtree(state)) {
+            self.nodes.clear();
+            self.nodes.push(Node::new(Action::NONE));
+        }
+        self.root_state = Some(state.clone());
+        self.greedy_player.evaluator.parameters = self.evaluation_parameters.clone();
+        self.iterations = 0;
+        while start_time.elapsed().as_millis() < self.time_limit {
+            self.iteration(state);
+            self.iterations += 1;
+        }
+        let info = self.search_info(start_time.elapsed().as_micros());
+        let action = match info.pv.first() {
+            Some(&action) => action,
+            None => {
+                gamerules::get_legal_actions(state, &mut self.al);
+                if self.al.size > 0 {
+                    self.al[0]
+                } else {
+                    Action::NONE
+                }
+            }
+        };
+        for observer in self.observers.iter_mut() {
+            if info.pv.is_empty() {
+                observer.on_search_end(action, None);
+            } else {
+                observer.on_iteration(&info);
+                observer.on_search_end(action, Some(&info));
+            }
+        }
+        action
+    }
+
+    // Reports the search like a single iteration of the minimax search. The principal
+    // variation follows the most visited children and the value is the win rate of its
+    // first action, converted back with the logistic function of the evaluation rollouts.
+    fn search_info(&self, elapsed: u128) -> SearchInfo {
+        let mut pv = Vec::new();
+        let mut value = 0;
+        let mut node = 0;
+        while let Some(child) = self.nodes[node]
+            .children()
+            .max_by_key(|&child| self.nodes[child].visits)
+        {
+            let child_node = self.nodes[child];
+            if child_node.visits == 0 {
+                break;
+            }
+            if pv.is_empty() {
+                let win_rate = (child_node.reward / child_node.visits as f32).clamp(0.001, 0.999);
+                value = (EVALUATION_SCALE * (win_rate / (1.0 - win_rate)).ln()).round() as i16;
+            }
+            pv.push(child_node.action);
+            node = child;
+        }
+        SearchInfo {
+            depth: pv.len(),
+            value,
+            mate_distance: None,
+            nodes: self.iterations,
+            nps: (self.iterations as f64 / (elapsed.max(1) as f64 / 1_000_000.0)).round(),
+            elapsed,
+            pv,
+            hashfull: self.nodes.len() * 1000 / MAX_NODES,
+            canceled: false,
+            multi_pv: 0,
+        }
+    }
+
+    pub fn root_visits(&self) -> u32 {
+        self.nodes.first().map_or(0, |root| root.visits)
+    }
+
+    fn iteration(&mut self, root_state: &GameState) {
+        let mut state = root_state.clone();
+        let mut path: Vec<usize> = vec![0];
+        let mut node = 0;
+        // Selection, stops at nodes without children because their player has no legal actions
+        while self.nodes[node].is_expanded
+            && self.nodes[node].children_count > 0
+            && !gamerules::is_game_over(&state)
+        {
+            node = self.select_child(node);
+            gamerules::do_action(&mut state, self.nodes[node].action);
+            path.push(node);
+        }
+        // Expansion
+        if !self.nodes[node].is_expanded
+            && !gamerules::is_game_over(&state)
+            && self.nodes.len() < MAX_NODES
+        {
+            self.expand(node, &state);
+            if self.nodes[node].children_count > 0 {
+                node = self.nodes[node].children_start
+                    + self.rng.next_u64() as usize % self.nodes[node].children_count as usize;
+                gamerules::do_action(&mut state, self.nodes[node].action);
+                path.push(node);
+            }
+        }
+        // Simulation
+        let red_reward = self.rollout(&mut state);
+        // Backpropagation
+        for (i, &node) in path.iter().enumerate() {
+            let node = &mut self.nodes[node];
+            node.visits += 1;
+            // The player that did the action leading to this node
+            let color = (root_state.ply as usize + i + 1) % 2;
+            node.reward += if color == RED {
+                red_reward
+            } else {
+                1.0 - red_reward
+            };
+        }
+    }
+
+    fn select_child(&self, node: usize) -> usize {
+        let parent = &self.nodes[node];
+        let log_visits = (parent.visits.max(1) as f32).ln();
+        let mut best_child = parent.children_start;
+        let mut best_value = f32::NEG_INFINITY;
+        for child in parent.children() {
+            let child_node = &self.nodes[child];
+            if child_node.visits == 0 {
+                return child;
+            }
+            let visits = child_node.visits as f32;
+            let value = child_node.reward / visits
+                + self.exploration_constant * (log_visits / visits).sqrt();
+            if value > best_value {
+                best_value = value;
+                best_child = child;
+            }
+        }
+        best_child
+    }
+
+    fn expand(&mut self, node: usize, state: &GameState) {
+        gamerules::get_legal_actions(state, &mut self.al);
+        self.nodes[node].children_start = self.nodes.len();
+        self.nodes[node].children_count = self.al.size as u16;
+        self.nodes[node].is_expanded = true;
+        for i in 0..self.al.size {
+            self.nodes.push(Node::new(self.al[i]));
+        }
+    }
+
+    // Returns the reward for red
+    fn rollout(&mut self, state: &mut GameState) -> f32 {
+        loop {
+            if gamerules::is_game_over(state) {
+                return (gamerules::game_result(state) as f32 + 1.0) / 2.0;
+            }
+            gamerules::get_legal_actions(state, &mut self.al);
+            if self.al.size == 0 {
+                // Like in the search, the player without legal actions wins
+                return if state.get_current_color() == RED {
+                    1.0
+                } else {
+                    0.0
+                };
+            }
+            if self.rollout_policy == RolloutPolicy::Evaluation {
+                let value = static_evaluation(state, &self.evaluation_parameters) as f32;
+                return 1.0 / (1.0 + (-value / EVALUATION_SCALE).exp());
+            }
+            let action = if self.rollout_policy == RolloutPolicy::Greedy
+                && !self
+                    .rng
+                    .next_u64()
+                    .is_multiple_of(GREEDY_ROLLOUT_RANDOMNESS)
+            {
+                self.greedy_player.get_action(state)
+            } else {
+                self.al[self.rng.next_u64() as usize % self.al.size]
+            };
+            gamerules::do_action(state, action);
+        }
+    }
+
+    // Searches the position among the grandchildren of the last root and makes it the new root
+    fn reuse_subtree(&mut self, state: &GameState) -> bool {
+        let root_state = match &self.root_state {
+            Some(root_state) if !self.nodes.is_empty() => root_state.clone(),
+            _ => return false,
+        };
+        for child in self.nodes[0].children() {
+            let mut child_state = root_state.clone();
+            gamerules::do_action(&mut child_state, self.nodes[child].action);
+            if Self::is_same_position(&child_state, state) {
+                self.reroot(child);
+                return true;
+            }
+            for grandchild in self.nodes[child].children() {
+                gamerules::do_action(&mut child_state, self.nodes[grandchild].action);
+                if Self::is_same_position(&child_state, state) {
+                    self.reroot(grandchild);
+                    return true;
+                }
+                gamerules::undo_action(&mut child_state, self.nodes[grandchild].action);
+            }
+        }
+        false
+    }
+
+    fn is_same_position(a: &GameState, b: &GameState) -> bool {
+        a.ply == b.ply && a.board == b.board && a.stacked == b.stacked && a.ambers == b.ambers
+    }
+
+    // Copies the subtree of the new root into a new node list
+    fn reroot(&mut self, new_root: usize) {
+        let mut nodes = Vec::with_capacity(self.nodes.len());
+        nodes.push(self.nodes[new_root]);
+        let mut index = 0;
+        while index < nodes.len() {
+            let node: Node = nodes[index];
+            if node.is_expanded {
+                let children_start = nodes.len();
+                for child in node.children() {
+                    nodes.push(self.nodes[child]);
+                }
+                nodes[index].children_start = children_start;
+            }
+            index += 1;
+        }
+        self.nodes = nodes;
+    }
+}
@@ -0,0 +1,127 @@
+use game_sdk::action::Action;
+use game_sdk::bitboard::FINISH_LINES;
+use game_sdk::gamerules::{COCKLE_PATTERN, GULL_PATTERN, SEAL_PATTERN, STARFISH_PATTERN};
+use game_sdk::gamestate::{GameState, COLORS};
+use game_sdk::piece::{COCKLE, GULL, PIECES, SEAL, STARFISH};
+
+// Fields that can be reached by the pieces of each type, [color][piece]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Attacks(pub [[u64; 4]; 2]);
+
+impl Attacks {
+    pub fn from_state(state: &GameState) -> Self {
+        let mut attacks = Self::default();
+        for color in COLORS {
+            for piece in PIECES {
+                attacks.0[color][piece as usize] =
+                    piece_attacks(color, piece, state.board[color][piece as usize]);
+            }
+        }
+        attacks
+    }
+
+    // Only the moved and the captured piece type can change their attacks, so only
+    // their bitboards have to be recalculated. Has to be called after the action was done.
+    pub fn update(&mut self, state: &GameState, action: Action) {
+        let other_color = state.get_current_color();
+        let color = other_color ^ 1;
+        let piece = action.piece() as u8;
+        self.0[color][piece as usize] =
+            piece_attacks(color, piece, state.board[color][piece as usize]);
+        if let Some((captured, _)) = state.undo[state.ply as usize - 1].get_capture() {
+            self.0[other_color][captured as usize] = piece_attacks(
+                other_color,
+                captured,
+                state.board[other_color][captured as usize],
+            );
+        }
+    }
+}
+
+#[inline(always)]
+pub fn piece_attacks(color: usize, piece: u8, mut pieces: u64) -> u64 {
+    let mut attacks = 0;
+    while pieces > 0 {
+        let position = pieces.trailing_zeros() as usize;
+        pieces ^= 1 << position;
+        attacks |= match piece {
+            COCKLE => COCKLE_PATTERN[position | color << 6],
+            GULL => GULL_PATTERN[position],
+            STARFISH => STARFISH_PATTERN[position | color << 6],
+            SEAL => SEAL_PATTERN[position],
+            _ => unreachable!(),
+        };
+    }
+    attacks
+}
+
+// Pieces of the color that could win an amber with the next action, by capturing a stack,
+// by capturing as a stack or by reaching the finish line if they aren't a seal. The pieces
+// that reach a target are found by attacking the targets with the other color, because its
+// patterns are the reversed patterns of the color.
+pub fn amber_threats(state: &GameState, color: usize, attacks: &Attacks) -> u64 {
+    let other_color = color ^ 1;
+    let opponent_pieces = state.occupied[other_color];
+    let finish_line = FINISH_LINES[color] & !state.occupied[color];
+    let mut threats = 0;
+    for piece in PIECES {
+        let pieces = state.board[color][piece as usize];
+        let piece_attacks_union = attacks.0[color][piece as usize];
+        let mut targets = piece_attacks_union & opponent_pieces & state.stacked;
+        if piece != SEAL {
+            targets |= piece_attacks_union & finish_line;
+        }
+        if targets > 0 {
+            threats |= pieces & piece_attacks(other_color, piece, targets);
+        }
+        let stacks = pieces & state.stacked;
+        if stacks > 0 {
+            let captures = piece_attacks_union & opponent_pieces;
+            threats |= stacks & piece_attacks(other_color, piece, captures);
+        }
+    }
+    threats
+}
+
+// Keeps the attacks of every position on the current search path
+#[derive(Clone)]
+pub struct AttackStack {
+    attacks: Vec<Attacks>,
+}
+
+impl Default for AttackStack {
+    fn default() -> Self {
+        Self {
+            attacks: Vec::with_capacity(128),
+        }
+    }
+}
+
+impl AttackStack {
+    pub fn reset(&mut self, state: &GameState) {
+        self.attacks.clear();
+        self.attacks.push(Attacks::from_state(state));
+    }
+
+    // Has to be called after every action that is done on the state
+    pub fn push(&mut self, state: &GameState, action: Action) {
+        let mut attacks = *self.top();
+        attacks.update(state, action);
+        self.attacks.push(attacks);
+    }
+
+    // Has to be called after every null action that is done on the state
+    pub fn push_null(&mut self) {
+        let attacks = *self.top();
+        self.attacks.push(attacks);
+    }
+
+    // Has to be called after every action that is undone on the state
+    pub fn pop(&mut self) {
+        self.attacks.pop();
+    }
+
+    pub fn top(&self) -> &Attacks {
+        self.attacks.last().unwrap()
+    }
+}
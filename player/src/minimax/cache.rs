@@ -1,69 +1,199 @@
 use game_sdk::action::Action;
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
 
-pub const TT_SIZE: usize = 2_usize.pow(23);
+pub const DEFAULT_HASH_SIZE: usize = 128;
+pub const BUCKET_SIZE: usize = 4;
 //pub const EVAL_CACHE_SIZE: usize = 2_usize.pow(25);
 
+// Layout of the packed entry data:
+// 00000000 00000000 11111111 11111111 value
+// 11111111 11111111 00000000 00000000 action
+// depth << 32, alpha << 40, beta << 41, valid << 42, generation << 43
+const DEPTH_SHIFT: u64 = 32;
+const ALPHA_BIT: u64 = 1 << 40;
+const BETA_BIT: u64 = 1 << 41;
+const VALID_BIT: u64 = 1 << 42;
+const GENERATION_SHIFT: u64 = 43;
+const GENERATION_MASK: u8 = 0x3F;
+// Entries of older searches lose this much depth per generation when choosing the
+// entry of a bucket that gets replaced
+const AGE_DEPTH_PENALTY: i32 = 8;
+
 #[derive(Clone, Copy)]
 pub struct TranspositionTableEntry {
     pub value: i16,
     pub action: Action,
     pub depth: u8,
-    pub hash: usize,
     pub alpha: bool,
     pub beta: bool,
 }
 
 impl TranspositionTableEntry {
-    pub fn is_valid(&self) -> bool {
-        self.depth != std::u8::MAX
+    fn pack(&self, generation: u8) -> u64 {
+        (self.value as u16 as u64)
+            | (self.action.bits() as u64) << 16
+            | (self.depth as u64) << DEPTH_SHIFT
+            | if self.alpha { ALPHA_BIT } else { 0 }
+            | if self.beta { BETA_BIT } else { 0 }
+            | VALID_BIT
+            | (generation as u64) << GENERATION_SHIFT
     }
-}
 
-impl Default for TranspositionTableEntry {
-    fn default() -> Self {
+    fn unpack(data: u64) -> Self {
         Self {
-            value: 0,
-            action: Action::NONE,
-            depth: std::u8::MAX,
-            hash: 0,
-            alpha: false,
-            beta: false,
+            value: data as u16 as i16,
+            action: Action::from_bits((data >> 16) as u16),
+            depth: (data >> DEPTH_SHIFT) as u8,
+            alpha: data & ALPHA_BIT > 0,
+            beta: data & BETA_BIT > 0,
         }
     }
 }
 
+#[inline(always)]
+fn depth_of(data: u64) -> u8 {
+    (data >> DEPTH_SHIFT) as u8
+}
+
+#[inline(always)]
+fn generation_of(data: u64) -> u8 {
+    (data >> GENERATION_SHIFT) as u8 & GENERATION_MASK
+}
+
+// The key of a slot is stored as hash ^ data, so an entry that was torn by two threads
+// writing to the same slot at the same time fails the verification and is ignored.
+#[derive(Default)]
+struct TranspositionTableSlot {
+    key: AtomicU64,
+    data: AtomicU64,
+}
+
+// Four slots of 16 bytes fill exactly one cache line
+#[derive(Default)]
+#[repr(align(64))]
+struct Bucket {
+    slots: [TranspositionTableSlot; BUCKET_SIZE],
+}
+
 pub struct TranspositionTable {
-    entries: Vec<TranspositionTableEntry>,
+    buckets: Vec<Bucket>,
+    generation: AtomicU8,
 }
 
 impl TranspositionTable {
-    pub fn insert(&mut self, hash: usize, new_entry: TranspositionTableEntry) {
-        let index = hash % TT_SIZE;
-        let entry = self.entries[index];
-        let is_valid = entry.is_valid();
-        if entry.depth <= new_entry.depth || !is_valid {
-            self.entries[index] = new_entry;
+    // Allocates a table that uses at most the given number of megabytes
+    pub fn with_size(megabytes: usize) -> Self {
+        let bucket_count = (megabytes * 1024 * 1024 / std::mem::size_of::<Bucket>()).max(1);
+        Self {
+            buckets: (0..bucket_count).map(|_| Bucket::default()).collect(),
+            generation: AtomicU8::new(0),
+        }
+    }
+
+    // Has to be called before every search so that entries of previous searches get replaced
+    pub fn new_search(&self) {
+        let generation = (self.generation() + 1) & GENERATION_MASK;
+        self.generation.store(generation, Ordering::Relaxed);
+    }
+
+    #[inline(always)]
+    fn generation(&self) -> u8 {
+        self.generation.load(Ordering::Relaxed)
+    }
+
+    #[inline(always)]
+    fn bucket(&self, hash: usize) -> &Bucket {
+        &self.buckets[hash % self.buckets.len()]
+    }
+
+    pub fn insert(&self, hash: usize, new_entry: TranspositionTableEntry) {
+        let generation = self.generation();
+        let bucket = self.bucket(hash);
+        let mut replaced_slot = &bucket.slots[0];
+        let mut lowest_priority = i32::MAX;
+        for slot in bucket.slots.iter() {
+            let data = slot.data.load(Ordering::Relaxed);
+            if data & VALID_BIT == 0 {
+                replaced_slot = slot;
+                break;
+            }
+            if slot.key.load(Ordering::Relaxed) ^ data == hash as u64 {
+                // Entries of the same position are only replaced by deeper searches
+                // unless they are from a previous search
+                if generation_of(data) == generation && depth_of(data) > new_entry.depth {
+                    return;
+                }
+                replaced_slot = slot;
+                break;
+            }
+            let age = (generation.wrapping_sub(generation_of(data)) & GENERATION_MASK) as i32;
+            let priority = depth_of(data) as i32 - AGE_DEPTH_PENALTY * age;
+            if priority < lowest_priority {
+                lowest_priority = priority;
+                replaced_slot = slot;
+            }
         }
+        let new_data = new_entry.pack(generation);
+        replaced_slot
+            .key
+            .store(hash as u64 ^ new_data, Ordering::Relaxed);
+        replaced_slot.data.store(new_data, Ordering::Relaxed);
     }
 
     pub fn lookup(&self, hash: usize) -> Option<TranspositionTableEntry> {
-        let entry = self.entries[hash % TT_SIZE];
-        if entry.is_valid() && entry.hash == hash {
-            Some(entry)
-        } else {
-            None
+        for slot in self.bucket(hash).slots.iter() {
+            let key = slot.key.load(Ordering::Relaxed);
+            let data = slot.data.load(Ordering::Relaxed);
+            if data & VALID_BIT > 0 && key ^ data == hash as u64 {
+                return Some(TranspositionTableEntry::unpack(data));
+            }
         }
+        None
+    }
+
+    // Removes all entries without reallocating the table
+    pub fn clear(&self) {
+        for bucket in self.buckets.iter() {
+            for slot in bucket.slots.iter() {
+                slot.key.store(0, Ordering::Relaxed);
+                slot.data.store(0, Ordering::Relaxed);
+            }
+        }
+        self.generation.store(0, Ordering::Relaxed);
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.buckets.len() * BUCKET_SIZE
     }
 
     pub fn get_fill_status(&self) -> usize {
-        self.entries.iter().filter(|e| e.is_valid()).count()
+        self.buckets
+            .iter()
+            .flat_map(|bucket| bucket.slots.iter())
+            .filter(|slot| slot.data.load(Ordering::Relaxed) & VALID_BIT > 0)
+            .count()
+    }
+
+    // Estimates the share of entries from the current search in per mille from the
+    // first thousand slots
+    pub fn hashfull(&self) -> usize {
+        let generation = self.generation();
+        let sample = &self.buckets[..self.buckets.len().min(1000 / BUCKET_SIZE)];
+        let filled = sample
+            .iter()
+            .flat_map(|bucket| bucket.slots.iter())
+            .filter(|slot| {
+                let data = slot.data.load(Ordering::Relaxed);
+                data & VALID_BIT > 0 && generation_of(data) == generation
+            })
+            .count();
+        filled * 1000 / (sample.len() * BUCKET_SIZE)
     }
 }
 
 impl Default for TranspositionTable {
     fn default() -> Self {
-        let entries = vec![TranspositionTableEntry::default(); TT_SIZE];
-        Self { entries }
+        Self::with_size(DEFAULT_HASH_SIZE)
     }
 }
 /*
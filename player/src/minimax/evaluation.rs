@@ -1,99 +1,133 @@
+use super::attacks::{amber_threats, Attacks};
 use game_sdk::bitboard::*;
 use game_sdk::gamerules::*;
 use game_sdk::gamestate::*;
 use game_sdk::piece::*;
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::io;
 
 pub const MATE_VALUE: i16 = 31_000;
+pub const PLY_LIMIT: f32 = 60.0;
+// Indices of the phases that are interpolated
+pub const OPENING: usize = 0;
+pub const ENDGAME: usize = 1;
 
-struct EvaluationParameters {
-    amber_value: f32,
-    turn_advantage: f32,
-    stacked_piece_value: f32,
-    capture_value: f32,
-    reachable_fields_value: [f32; 4],
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct EvaluationParameters {
+    pub amber_value: f32,
+    pub turn_advantage: f32,
+    pub stacked_piece_value: f32,
+    pub capture_value: f32,
+    pub tie_break_value: f32,
+    pub hanging_piece_value: f32,
+    pub defended_stack_value: f32,
+    pub multiple_amber_threats_value: f32,
+    pub reachable_fields_value: [f32; 4],
+    // [phase][piece]
+    pub piece_values: [[f32; 4]; 2],
+    // [phase][piece][y][x] from the perspective of red, mirrored for blue
+    pub piece_square_tables: [[[[f32; 8]; 8]; 4]; 2],
 }
 // TODO: Tune parameters
-const DEFAULT_PARAMETERS: EvaluationParameters = EvaluationParameters {
+pub const DEFAULT_PARAMETERS: EvaluationParameters = EvaluationParameters {
     amber_value: 100.0,
     turn_advantage: 3.0,
     stacked_piece_value: 20.0,
     capture_value: 20.0,
+    tie_break_value: 40.0,
+    hanging_piece_value: -10.0,
+    defended_stack_value: 10.0,
+    multiple_amber_threats_value: 40.0,
     reachable_fields_value: [1.0, 1.0, 1.0, 1.0],
+    piece_values: [[10.0, 10.0, 10.0, 10.0], [10.0, 10.0, 10.0, 10.0]],
+    piece_square_tables: [[[[0.0; 8]; 8]; 4]; 2],
 };
 
+impl EvaluationParameters {
+    pub fn from_json(json: &str) -> io::Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    pub fn load(path: &str) -> io::Result<Self> {
+        Self::from_json(&fs::read_to_string(path)?)
+    }
+
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        fs::write(path, self.to_json())
+    }
+
+    // All weights that can be changed by the tuner
+    pub fn weights_mut(&mut self) -> Vec<&mut f32> {
+        let mut weights = vec![
+            &mut self.amber_value,
+            &mut self.turn_advantage,
+            &mut self.stacked_piece_value,
+            &mut self.capture_value,
+            &mut self.tie_break_value,
+            &mut self.hanging_piece_value,
+            &mut self.defended_stack_value,
+            &mut self.multiple_amber_threats_value,
+        ];
+        weights.extend(self.reachable_fields_value.iter_mut());
+        weights.extend(self.piece_values.iter_mut().flatten());
+        weights.extend(
+            self.piece_square_tables
+                .iter_mut()
+                .flatten()
+                .flatten()
+                .flatten(),
+        );
+        weights
+    }
+}
+
+impl Default for EvaluationParameters {
+    fn default() -> Self {
+        DEFAULT_PARAMETERS
+    }
+}
+
+// 0 at the start of the game, 1 at the ply limit
+#[inline(always)]
+pub fn game_phase(state: &GameState) -> f32 {
+    (state.ply as f32 / PLY_LIMIT).min(1.0)
+}
+
+#[inline(always)]
+fn interpolate(values: [f32; 2], phase: f32) -> f32 {
+    values[OPENING] + (values[ENDGAME] - values[OPENING]) * phase
+}
+
 #[derive(Default)]
 struct ReachableFields {
     //pieces: [u64; 4],
     //stacks: [u64; 4],
     cockle: u64,
-    cockle_stack: u64,
     gull: u64,
-    gull_stack: u64,
     starfish: u64,
-    starfish_stack: u64,
     seal: u64,
-    seal_stack: u64,
     all: u64,
     all_stacked: u64,
 }
 
 impl ReachableFields {
-    pub fn for_color(color: usize, board: &[u64; 4], stacked: u64) -> Self {
-        let color = (color as usize) << 6;
-        let mut reachable_fields = ReachableFields::default();
-        // Get reachable fileds for cockles
-        let mut cockles = board[COCKLE as usize];
-        while cockles > 0 {
-            let position = cockles.trailing_zeros();
-            let bit = 1 << position;
-            cockles ^= bit;
-            if bit & stacked != 0 {
-                reachable_fields.cockle_stack |= bit;
-            }
-            reachable_fields.cockle |= COCKLE_PATTERN[position as usize | color];
-        }
-        // Get reachable fields for gulls
-        let mut gulls = board[GULL as usize];
-        while gulls > 0 {
-            let position = gulls.trailing_zeros();
-            let bit = 1 << position;
-            gulls ^= bit;
-            if bit & stacked != 0 {
-                reachable_fields.gull_stack |= bit;
-            }
-            reachable_fields.gull |= GULL_PATTERN[position as usize];
-        }
-        // Get reachable fields for starfish
-        let mut starfish = board[STARFISH as usize];
-        while starfish > 0 {
-            let position = starfish.trailing_zeros();
-            let bit = 1 << position;
-            starfish ^= bit;
-            if bit & stacked != 0 {
-                reachable_fields.starfish_stack |= bit;
-            }
-            reachable_fields.starfish |= STARFISH_PATTERN[position as usize | color];
-        }
-        // Get reachable fields for seals
-        let mut seals = board[SEAL as usize];
-        while seals > 0 {
-            let position = seals.trailing_zeros();
-            let bit = 1 << position;
-            seals ^= bit;
-            if bit & stacked != 0 {
-                reachable_fields.seal_stack |= bit;
-            }
-            reachable_fields.seal |= SEAL_PATTERN[position as usize];
+    #[inline(always)]
+    pub fn for_color(attacks: &[u64; 4], occupied: u64, stacked: u64) -> Self {
+        let [cockle, gull, starfish, seal] = *attacks;
+        Self {
+            cockle,
+            gull,
+            starfish,
+            seal,
+            all: cockle | gull | starfish | seal,
+            all_stacked: occupied & stacked,
         }
-        reachable_fields.all = reachable_fields.cockle
-            | reachable_fields.gull
-            | reachable_fields.starfish
-            | reachable_fields.seal;
-        reachable_fields.all_stacked = reachable_fields.cockle_stack
-            | reachable_fields.gull_stack
-            | reachable_fields.starfish_stack
-            | reachable_fields.seal_stack;
-        reachable_fields
     }
 }
 
@@ -116,14 +150,180 @@ impl Captures {
     }
 }
 
-pub fn static_evaluation(state: &GameState) -> i16 {
-    let red_reachable_fields = ReachableFields::for_color(RED, &state.board[RED], state.stacked);
-    let blue_reachable_fields = ReachableFields::for_color(BLUE, &state.board[BLUE], state.stacked);
-    let is_reds_turn = state.ply % 2 == 0;
+// Pieces are defended if a piece of the same color could recapture on their field
+struct AttackMap {
+    hanging: u64,
+    defended_stacks: u64,
+    // Number of pieces that could win an amber with the next move
+    amber_threats: u32,
+}
+
+impl AttackMap {
+    #[inline(always)]
+    pub fn for_color(
+        state: &GameState,
+        color: usize,
+        attacks: &Attacks,
+        my_reachable_fields: &ReachableFields,
+        opponent_reachable_fields: &ReachableFields,
+    ) -> Self {
+        let my_pieces = state.occupied[color];
+        Self {
+            hanging: my_pieces & opponent_reachable_fields.all & !my_reachable_fields.all,
+            defended_stacks: my_pieces & state.stacked & my_reachable_fields.all,
+            amber_threats: amber_threats(state, color, attacks).count_ones(),
+        }
+    }
+}
+
+pub const FEATURES: usize = 12;
+pub const FEATURE_NAMES: [&str; FEATURES] = [
+    "Ambers",
+    "Stacked pieces",
+    "Captures",
+    "Cockle mobility",
+    "Gull mobility",
+    "Starfish mobility",
+    "Seal mobility",
+    "Pieces",
+    "Piece-square tables",
+    "Hanging pieces",
+    "Defended stacks",
+    "Multiple amber threats",
+];
+
+// Raw value and weighted contribution of every feature
+type Features = [(f32, f32); FEATURES];
+
+// Explains how the value of a position is put together
+#[derive(Clone, Debug)]
+pub struct EvaluationTrace {
+    // [color][feature]
+    pub features: [Features; 2],
+    pub turn_advantage: f32,
+    pub tie_break: f32,
+    // Value of the winning move check, if it decided the evaluation
+    pub mate_shortcut: Option<i16>,
+    pub value: i16,
+}
+
+impl Display for EvaluationTrace {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Feature                  Red raw  Red value   Blue raw Blue value"
+        )?;
+        for (i, name) in FEATURE_NAMES.iter().enumerate() {
+            let (red_raw, red_value) = self.features[RED][i];
+            let (blue_raw, blue_value) = self.features[BLUE][i];
+            writeln!(
+                f,
+                "{:22} {:9.2} {:10.2} {:10.2} {:10.2}",
+                name, red_raw, red_value, blue_raw, blue_value
+            )?;
+        }
+        writeln!(f, "Turn advantage: {:.2}", self.turn_advantage)?;
+        writeln!(f, "Tie-break: {:.2}", self.tie_break)?;
+        if let Some(value) = self.mate_shortcut {
+            writeln!(f, "Winning move check triggered: {}", value)?;
+        }
+        write!(f, "Value: {}", self.value)
+    }
+}
+
+pub fn static_evaluation(state: &GameState, parameters: &EvaluationParameters) -> i16 {
+    evaluate_with_attacks(state, parameters, &Attacks::from_state(state))
+}
+
+// Same as static_evaluation, but uses attacks that were already calculated, e.g. by an AttackStack
+pub fn evaluate_with_attacks(
+    state: &GameState,
+    parameters: &EvaluationParameters,
+    attacks: &Attacks,
+) -> i16 {
+    let red_reachable_fields =
+        ReachableFields::for_color(&attacks.0[RED], state.occupied[RED], state.stacked);
+    let blue_reachable_fields =
+        ReachableFields::for_color(&attacks.0[BLUE], state.occupied[BLUE], state.stacked);
     let red_captures =
         Captures::for_color(&red_reachable_fields, state.occupied[BLUE], state.stacked);
     let blue_captures =
         Captures::for_color(&blue_reachable_fields, state.occupied[RED], state.stacked);
+    if let Some(value) = mate_shortcut(
+        state,
+        &red_reachable_fields,
+        &blue_reachable_fields,
+        &red_captures,
+        &blue_captures,
+    ) {
+        return value;
+    }
+    let features = evaluate_features(
+        state,
+        parameters,
+        attacks,
+        &red_reachable_fields,
+        &blue_reachable_fields,
+        &red_captures,
+        &blue_captures,
+    );
+    let red: f32 = features[RED].iter().map(|feature| feature.1).sum();
+    let blue: f32 = features[BLUE].iter().map(|feature| feature.1).sum();
+    (red - blue + turn_advantage(state, parameters) + tie_break_value(state, parameters)).round()
+        as i16
+}
+
+// Same as static_evaluation, but keeps the value of every feature
+pub fn evaluate_with_trace(
+    state: &GameState,
+    parameters: &EvaluationParameters,
+) -> EvaluationTrace {
+    let attacks = Attacks::from_state(state);
+    let red_reachable_fields =
+        ReachableFields::for_color(&attacks.0[RED], state.occupied[RED], state.stacked);
+    let blue_reachable_fields =
+        ReachableFields::for_color(&attacks.0[BLUE], state.occupied[BLUE], state.stacked);
+    let red_captures =
+        Captures::for_color(&red_reachable_fields, state.occupied[BLUE], state.stacked);
+    let blue_captures =
+        Captures::for_color(&blue_reachable_fields, state.occupied[RED], state.stacked);
+    let mut trace = EvaluationTrace {
+        features: evaluate_features(
+            state,
+            parameters,
+            &attacks,
+            &red_reachable_fields,
+            &blue_reachable_fields,
+            &red_captures,
+            &blue_captures,
+        ),
+        turn_advantage: turn_advantage(state, parameters),
+        tie_break: tie_break_value(state, parameters),
+        mate_shortcut: mate_shortcut(
+            state,
+            &red_reachable_fields,
+            &blue_reachable_fields,
+            &red_captures,
+            &blue_captures,
+        ),
+        value: 0,
+    };
+    trace.value = trace.mate_shortcut.unwrap_or_else(|| {
+        let red: f32 = trace.features[RED].iter().map(|feature| feature.1).sum();
+        let blue: f32 = trace.features[BLUE].iter().map(|feature| feature.1).sum();
+        (red - blue + trace.turn_advantage + trace.tie_break).round() as i16
+    });
+    trace
+}
+
+fn mate_shortcut(
+    state: &GameState,
+    red_reachable_fields: &ReachableFields,
+    blue_reachable_fields: &ReachableFields,
+    red_captures: &Captures,
+    blue_captures: &Captures,
+) -> Option<i16> {
+    let is_reds_turn = state.ply.is_multiple_of(2);
     // Check whether the current player has a winning move
     if is_reds_turn {
         if state.ambers[RED] == 1
@@ -134,7 +334,7 @@ pub fn static_evaluation(state: &GameState) -> i16 {
                     & FINISH_LINES[RED]
                     > 0)
         {
-            return MATE_VALUE;
+            return Some(MATE_VALUE);
         }
     } else if state.ambers[BLUE] == 1
         && ((blue_captures.stack_captures > 0 || blue_captures.captures_stack > 0)
@@ -144,7 +344,7 @@ pub fn static_evaluation(state: &GameState) -> i16 {
                 & FINISH_LINES[BLUE]
                 > 0)
     {
-        return -MATE_VALUE;
+        return Some(-MATE_VALUE);
     }
     // Check whether the other player has a winning move
     if !is_reds_turn {
@@ -153,59 +353,168 @@ pub fn static_evaluation(state: &GameState) -> i16 {
             && (state.ambers[BLUE] == 0
                 || blue_captures.stack_captures | blue_captures.captures_stack == 0)
         {
-            return MATE_VALUE;
+            return Some(MATE_VALUE);
         }
     } else if blue_captures.stack_captures | blue_captures.captures_stack > 1
         && state.ambers[BLUE] == 1
         && (state.ambers[RED] == 0
             || red_captures.stack_captures | red_captures.captures_stack == 0)
     {
-        return -MATE_VALUE;
+        return Some(-MATE_VALUE);
     }
+    None
+}
 
+fn evaluate_features(
+    state: &GameState,
+    parameters: &EvaluationParameters,
+    attacks: &Attacks,
+    red_reachable_fields: &ReachableFields,
+    blue_reachable_fields: &ReachableFields,
+    red_captures: &Captures,
+    blue_captures: &Captures,
+) -> [Features; 2] {
+    let red_attack_map = AttackMap::for_color(
+        state,
+        RED,
+        attacks,
+        red_reachable_fields,
+        blue_reachable_fields,
+    );
+    let blue_attack_map = AttackMap::for_color(
+        state,
+        BLUE,
+        attacks,
+        blue_reachable_fields,
+        red_reachable_fields,
+    );
     let red = evaluate_color(
         state,
+        parameters,
         RED,
-        &red_reachable_fields,
-        //&blue_reachable_fields,
-        &red_captures,
-        //&blue_captures,
+        red_reachable_fields,
+        //blue_reachable_fields,
+        red_captures,
+        //blue_captures,
+        &red_attack_map,
     );
     let blue = evaluate_color(
         state,
+        parameters,
         BLUE,
-        &blue_reachable_fields,
-        //&red_reachable_fields,
-        &blue_captures,
-        //&red_captures,
+        blue_reachable_fields,
+        //red_reachable_fields,
+        blue_captures,
+        //red_captures,
+        &blue_attack_map,
     );
-    let turn_advantage =
-        DEFAULT_PARAMETERS.turn_advantage * if state.ply % 2 == 0 { 1.0 } else { -1.0 };
-    (red - blue + turn_advantage).round() as i16
+    [red, blue]
+}
+
+fn turn_advantage(state: &GameState, parameters: &EvaluationParameters) -> f32 {
+    parameters.turn_advantage
+        * if state.ply.is_multiple_of(2) {
+            1.0
+        } else {
+            -1.0
+        }
+}
+
+// The tie-break decides the game if the ambers are still equal at the ply limit, so it
+// becomes more important the closer the game gets to the end.
+fn tie_break_value(state: &GameState, parameters: &EvaluationParameters) -> f32 {
+    if state.ambers[RED] != state.ambers[BLUE] {
+        return 0.0;
+    }
+    let phase = game_phase(state);
+    parameters.tie_break_value * phase * phase * tie_break(state) as f32
+}
+
+#[inline(always)]
+fn weighted(raw: f32, weight: f32) -> (f32, f32) {
+    (raw, raw * weight)
 }
 
 fn evaluate_color(
     state: &GameState,
+    parameters: &EvaluationParameters,
     color: usize,
     my_reachable_fields: &ReachableFields,
     //opponent_reachable_fields: &ReachableFields,
     my_captures: &Captures,
     //opponent_captures: &Captures,
-) -> f32 {
-    let amber_value = DEFAULT_PARAMETERS.amber_value * (state.ambers[color] as f32);
-    let stacked_piece_value = DEFAULT_PARAMETERS.stacked_piece_value
-        * ((state.stacked & state.occupied[color]).count_ones() as f32);
-    let capture_value = DEFAULT_PARAMETERS.capture_value
-        * ((my_captures.captures_stack | my_captures.stack_captures).count_ones() as f32);
-    let reachable_fields_value = DEFAULT_PARAMETERS.reachable_fields_value[COCKLE as usize]
-        * (my_reachable_fields.cockle.count_ones() as f32)
-        + DEFAULT_PARAMETERS.reachable_fields_value[GULL as usize]
-            * (my_reachable_fields.gull.count_ones() as f32)
-        + DEFAULT_PARAMETERS.reachable_fields_value[STARFISH as usize]
-            * (my_reachable_fields.starfish.count_ones() as f32)
-        + DEFAULT_PARAMETERS.reachable_fields_value[SEAL as usize]
-            * (my_reachable_fields.seal.count_ones() as f32);
-    // TODO: Piece values
+    my_attack_map: &AttackMap,
+) -> Features {
+    let phase = game_phase(state);
+    let mut piece_value = 0.0;
+    let mut piece_square_value = 0.0;
+    for piece in 0..4 {
+        let mut pieces = state.board[color][piece];
+        piece_value += interpolate(
+            [
+                parameters.piece_values[OPENING][piece],
+                parameters.piece_values[ENDGAME][piece],
+            ],
+            phase,
+        ) * pieces.count_ones() as f32;
+        while pieces > 0 {
+            let position = pieces.trailing_zeros() as usize;
+            pieces ^= 1 << position;
+            // Blue moves towards x = 0, so its squares are mirrored
+            let position = if color == RED { position } else { position ^ 7 };
+            let (x, y) = (position % 8, position / 8);
+            piece_square_value += interpolate(
+                [
+                    parameters.piece_square_tables[OPENING][piece][y][x],
+                    parameters.piece_square_tables[ENDGAME][piece][y][x],
+                ],
+                phase,
+            );
+        }
+    }
+    let piece_count = state.occupied[color].count_ones() as f32;
+    // The opponent can't stop more than one threat with a single move
+    let has_multiple_amber_threats = my_attack_map.amber_threats > 1;
     // TODO: More evaluation features
-    amber_value + stacked_piece_value + capture_value + reachable_fields_value
+    [
+        weighted(state.ambers[color] as f32, parameters.amber_value),
+        weighted(
+            (state.stacked & state.occupied[color]).count_ones() as f32,
+            parameters.stacked_piece_value,
+        ),
+        weighted(
+            (my_captures.captures_stack | my_captures.stack_captures).count_ones() as f32,
+            parameters.capture_value,
+        ),
+        weighted(
+            my_reachable_fields.cockle.count_ones() as f32,
+            parameters.reachable_fields_value[COCKLE as usize],
+        ),
+        weighted(
+            my_reachable_fields.gull.count_ones() as f32,
+            parameters.reachable_fields_value[GULL as usize],
+        ),
+        weighted(
+            my_reachable_fields.starfish.count_ones() as f32,
+            parameters.reachable_fields_value[STARFISH as usize],
+        ),
+        weighted(
+            my_reachable_fields.seal.count_ones() as f32,
+            parameters.reachable_fields_value[SEAL as usize],
+        ),
+        (piece_count, piece_value),
+        (piece_count, piece_square_value),
+        weighted(
+            my_attack_map.hanging.count_ones() as f32,
+            parameters.hanging_piece_value,
+        ),
+        weighted(
+            my_attack_map.defended_stacks.count_ones() as f32,
+            parameters.defended_stack_value,
+        ),
+        weighted(
+            has_multiple_amber_threats as u8 as f32,
+            parameters.multiple_amber_threats_value,
+        ),
+    ]
 }
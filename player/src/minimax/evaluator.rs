@@ -0,0 +1,107 @@
+use super::attacks::AttackStack;
+use super::evaluation::{evaluate_with_attacks, EvaluationParameters};
+use super::nnue::{AccumulatorStack, NeuralNetwork};
+use game_sdk::action::Action;
+use game_sdk::gamestate::GameState;
+use std::sync::Arc;
+
+// Evaluates positions from the perspective of red. The players tell the evaluator about
+// every action on their search path, so that it can update its state incrementally.
+// reset has to be called before the first evaluation.
+pub trait Evaluator: Clone + Send {
+    fn reset(&mut self, _state: &GameState) {}
+
+    // Called after the action was done on the state
+    fn push(&mut self, _state: &GameState, _action: Action) {}
+
+    // Called after a null action was done on the state
+    fn push_null(&mut self, _state: &GameState) {}
+
+    // Called after an action or a null action was undone on the state
+    fn pop(&mut self) {}
+
+    fn evaluate(&self, state: &GameState) -> i16;
+}
+
+// The hand-written evaluation
+#[derive(Clone, Default)]
+pub struct StaticEvaluator {
+    pub parameters: EvaluationParameters,
+    attacks: AttackStack,
+}
+
+impl StaticEvaluator {
+    pub fn new(parameters: EvaluationParameters) -> Self {
+        Self {
+            parameters,
+            attacks: AttackStack::default(),
+        }
+    }
+}
+
+impl Evaluator for StaticEvaluator {
+    fn reset(&mut self, state: &GameState) {
+        self.attacks.reset(state);
+    }
+
+    #[inline(always)]
+    fn push(&mut self, state: &GameState, action: Action) {
+        self.attacks.push(state, action);
+    }
+
+    #[inline(always)]
+    fn push_null(&mut self, _state: &GameState) {
+        self.attacks.push_null();
+    }
+
+    #[inline(always)]
+    fn pop(&mut self) {
+        self.attacks.pop();
+    }
+
+    #[inline(always)]
+    fn evaluate(&self, state: &GameState) -> i16 {
+        evaluate_with_attacks(state, &self.parameters, self.attacks.top())
+    }
+}
+
+#[derive(Clone)]
+pub struct NeuralEvaluator {
+    pub network: Arc<NeuralNetwork>,
+    accumulators: AccumulatorStack,
+}
+
+impl NeuralEvaluator {
+    pub fn new(network: Arc<NeuralNetwork>) -> Self {
+        Self {
+            network,
+            accumulators: AccumulatorStack::default(),
+        }
+    }
+}
+
+impl Evaluator for NeuralEvaluator {
+    fn reset(&mut self, state: &GameState) {
+        self.accumulators.reset(&self.network, state);
+    }
+
+    #[inline(always)]
+    fn push(&mut self, state: &GameState, _action: Action) {
+        self.accumulators.push(&self.network, state);
+    }
+
+    #[inline(always)]
+    fn push_null(&mut self, state: &GameState) {
+        self.accumulators.push(&self.network, state);
+    }
+
+    #[inline(always)]
+    fn pop(&mut self) {
+        self.accumulators.pop();
+    }
+
+    #[inline(always)]
+    fn evaluate(&self, _state: &GameState) -> i16 {
+        self.network.evaluate(self.accumulators.top())
+    }
+}
@@ -0,0 +1,54 @@
+use super::search::MAX_SEARCH_DEPTH;
+
+// Conditions under which the iterative deepening stops. The search also stops at any time
+// when the stop handle of the searcher is set.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SearchLimits {
+    pub max_depth: usize,
+    pub max_nodes: usize,
+    // Time limit in milliseconds, None searches until another limit is reached
+    pub movetime: Option<u128>,
+}
+
+impl Default for SearchLimits {
+    fn default() -> Self {
+        Self::movetime(1970)
+    }
+}
+
+impl SearchLimits {
+    // Only stops when the search tree is exhausted or the stop handle is set
+    pub fn infinite() -> Self {
+        Self {
+            max_depth: MAX_SEARCH_DEPTH,
+            max_nodes: usize::MAX,
+            movetime: None,
+        }
+    }
+
+    pub fn movetime(movetime: u128) -> Self {
+        Self {
+            movetime: Some(movetime),
+            ..Self::infinite()
+        }
+    }
+
+    pub fn depth(max_depth: usize) -> Self {
+        Self {
+            max_depth,
+            ..Self::infinite()
+        }
+    }
+
+    pub fn nodes(max_nodes: usize) -> Self {
+        Self {
+            max_nodes,
+            ..Self::infinite()
+        }
+    }
+
+    #[inline(always)]
+    pub fn is_time_exceeded(&self, elapsed: u128) -> bool {
+        matches!(self.movetime, Some(movetime) if elapsed >= movetime)
+    }
+}
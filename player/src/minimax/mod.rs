@@ -1,4 +1,12 @@
+pub mod attacks;
 pub mod cache;
 pub mod evaluation;
+pub mod evaluator;
+pub mod limits;
 pub mod move_ordering;
+pub mod nnue;
+pub mod proof_number_search;
 pub mod search;
+pub mod search_info;
+pub mod selectivity;
+pub mod time_manager;
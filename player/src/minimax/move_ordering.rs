@@ -5,15 +5,57 @@ use game_sdk::action::MAX_ACTIONS;
 use game_sdk::gamerules;
 use game_sdk::gamestate::GameState;
 
-pub const PV_ACTION_VALUE: u64 = std::u64::MAX;
-pub const TT_ACTION_VALUE: u64 = std::u64::MAX - 1;
-pub const KILLER_MOVE_VALUE: u64 = std::u64::MAX - 2;
+pub const PV_ACTION_VALUE: u64 = u64::MAX;
+pub const TT_ACTION_VALUE: u64 = u64::MAX - 1;
+pub const KILLER_MOVE_VALUE: u64 = u64::MAX - 2;
+pub const COUNTER_MOVE_VALUE: u64 = u64::MAX - 3;
 pub const AMBER_CAPUTURE_VALUE: u64 = 50_000;
 pub const CAPTURE_VALUE: u64 = 1_000;
 
+// Indices of the actions that are tried without generating all actions
+const PV_ACTION: usize = 0;
+const TT_ACTION: usize = 1;
+const FIRST_KILLER: usize = 2;
+const SECOND_KILLER: usize = 3;
+const COUNTER_MOVE: usize = 4;
+const SPECIAL_ACTION_VALUES: [u64; 5] = [
+    PV_ACTION_VALUE,
+    TT_ACTION_VALUE,
+    KILLER_MOVE_VALUE,
+    KILLER_MOVE_VALUE,
+    COUNTER_MOVE_VALUE,
+];
+
+// The actions of a node are generated lazily, so that a cutoff by one of the first
+// actions saves the generation of the others.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Stage {
+    PvAction,
+    TtAction,
+    // Amber captures and actions that move a light piece onto the finish line
+    GenerateAmberActions,
+    AmberActions,
+    FirstKiller,
+    SecondKiller,
+    CounterMove,
+    // Plain captures and quiet actions
+    GenerateRemainingActions,
+    RemainingActions,
+    Done,
+}
+
 pub struct MoveOrderer {
     pub als: ActionListStack,
     values: [[u64; MAX_ACTIONS]; MAX_SEARCH_DEPTH],
+    stages: [Stage; MAX_SEARCH_DEPTH],
+    special_actions: [[Action; 5]; MAX_SEARCH_DEPTH],
+    // The opponent's last action and the own action before it
+    previous_actions: [[Action; 2]; MAX_SEARCH_DEPTH],
+    // [color][piece][to] of the previous action
+    counter_moves: [[[Action; 64]; 4]; 2],
+    // Success of an action after the previous action and after the own action before it,
+    // indexed by piece and destination of both actions
+    continuation_history: [Vec<u64>; 2],
 }
 
 impl Default for MoveOrderer {
@@ -21,54 +63,243 @@ impl Default for MoveOrderer {
         MoveOrderer {
             als: ActionListStack::with_size(MAX_SEARCH_DEPTH),
             values: [[0; MAX_ACTIONS]; MAX_SEARCH_DEPTH],
+            stages: [Stage::Done; MAX_SEARCH_DEPTH],
+            special_actions: [[Action::NONE; 5]; MAX_SEARCH_DEPTH],
+            previous_actions: [[Action::NONE; 2]; MAX_SEARCH_DEPTH],
+            counter_moves: [[[Action::NONE; 64]; 4]; 2],
+            continuation_history: [vec![0; 4 * 64 * 4 * 64], vec![0; 4 * 64 * 4 * 64]],
         }
     }
 }
 
+#[inline(always)]
+fn continuation_index(previous_action: Action, action: Action) -> usize {
+    (previous_action.piece() as usize) << 14
+        | (previous_action.to() as usize) << 8
+        | (action.piece() as usize) << 6
+        | action.to() as usize
+}
+
+#[inline(always)]
+fn is_amber_action(action: Action, color: usize) -> bool {
+    action.is_amber_capture() || action.is_promotion(color)
+}
+
 impl MoveOrderer {
-    #[allow(clippy::too_many_arguments)]
-    pub fn generate_moves(
+    // Prepares the staged generation of all legal actions. previous_actions are the
+    // opponent's last action and the own action before it, Action::NONE if unknown.
+    pub fn start(
         &mut self,
         state: &GameState,
         depth: usize,
         pv_action: Action,
         tt_action: Action,
         killer_heuristic: &[Action; 2],
+        previous_actions: [Action; 2],
+    ) {
+        let counter_move = if previous_actions[0] == Action::NONE {
+            Action::NONE
+        } else {
+            self.counter_moves[state.get_current_color()][previous_actions[0].piece() as usize]
+                [previous_actions[0].to() as usize]
+        };
+        self.stages[depth] = Stage::PvAction;
+        self.special_actions[depth] = [
+            pv_action,
+            tt_action,
+            killer_heuristic[0],
+            killer_heuristic[1],
+            counter_move,
+        ];
+        self.previous_actions[depth] = previous_actions;
+    }
+
+    pub fn next_with_value(
+        &mut self,
+        state: &GameState,
+        depth: usize,
+        history_heuristic: &[[u64; 64]; 64],
+        butterfly_heuristic: &[[u64; 64]; 64],
+    ) -> (Action, u64) {
+        loop {
+            let special_action = match self.stages[depth] {
+                Stage::PvAction => {
+                    self.stages[depth] = Stage::TtAction;
+                    PV_ACTION
+                }
+                Stage::TtAction => {
+                    self.stages[depth] = Stage::GenerateAmberActions;
+                    TT_ACTION
+                }
+                Stage::GenerateAmberActions => {
+                    self.generate_amber_actions(state, depth);
+                    self.stages[depth] = Stage::AmberActions;
+                    continue;
+                }
+                Stage::AmberActions => {
+                    if self.als[depth].size > 0 {
+                        return self.pick_best(depth);
+                    }
+                    self.stages[depth] = Stage::FirstKiller;
+                    continue;
+                }
+                Stage::FirstKiller => {
+                    self.stages[depth] = Stage::SecondKiller;
+                    FIRST_KILLER
+                }
+                Stage::SecondKiller => {
+                    self.stages[depth] = Stage::CounterMove;
+                    SECOND_KILLER
+                }
+                Stage::CounterMove => {
+                    self.stages[depth] = Stage::GenerateRemainingActions;
+                    COUNTER_MOVE
+                }
+                Stage::GenerateRemainingActions => {
+                    self.generate_remaining_actions(
+                        state,
+                        depth,
+                        history_heuristic,
+                        butterfly_heuristic,
+                    );
+                    self.stages[depth] = Stage::RemainingActions;
+                    continue;
+                }
+                Stage::RemainingActions => {
+                    if self.als[depth].size > 0 {
+                        return self.pick_best(depth);
+                    }
+                    self.stages[depth] = Stage::Done;
+                    continue;
+                }
+                Stage::Done => return (Action::NONE, 0),
+            };
+            if self.is_special_action_playable(state, depth, special_action) {
+                return (
+                    self.special_actions[depth][special_action],
+                    SPECIAL_ACTION_VALUES[special_action],
+                );
+            }
+        }
+    }
+
+    fn is_special_action_playable(&self, state: &GameState, depth: usize, index: usize) -> bool {
+        let action = self.special_actions[depth][index];
+        action != Action::NONE
+            && !self.special_actions[depth][..index].contains(&action)
+            // Killers and counter moves that are amber actions were already tried
+            && (index < FIRST_KILLER || !is_amber_action(action, state.get_current_color()))
+            && gamerules::is_legal_action(state, action)
+    }
+
+    fn generate_amber_actions(&mut self, state: &GameState, depth: usize) {
+        let color = state.get_current_color();
+        gamerules::get_tactical_actions(state, &mut self.als[depth]);
+        let mut size = 0;
+        for i in 0..self.als[depth].size {
+            let action = self.als[depth][i];
+            if !is_amber_action(action, color)
+                || self.special_actions[depth][..FIRST_KILLER].contains(&action)
+            {
+                continue;
+            }
+            self.als[depth].swap(size, i);
+            self.values[depth][size] = AMBER_CAPUTURE_VALUE;
+            size += 1;
+        }
+        self.als[depth].size = size;
+    }
+
+    fn generate_remaining_actions(
+        &mut self,
+        state: &GameState,
+        depth: usize,
         history_heuristic: &[[u64; 64]; 64],
         butterfly_heuristic: &[[u64; 64]; 64],
     ) {
         let color = state.get_current_color();
+        let previous_actions = self.previous_actions[depth];
         // TODO: Consider the number of ambers for action.is_amber_capture()
         gamerules::get_legal_actions(state, &mut self.als[depth]);
+        let mut size = 0;
+        for i in 0..self.als[depth].size {
+            let action = self.als[depth][i];
+            if is_amber_action(action, color) || self.special_actions[depth].contains(&action) {
+                continue;
+            }
+            let mut history_value = history_heuristic[action.from() as usize][action.to() as usize];
+            for (previous_action, continuation_history) in previous_actions
+                .iter()
+                .zip(self.continuation_history.iter())
+            {
+                if *previous_action != Action::NONE {
+                    history_value +=
+                        continuation_history[continuation_index(*previous_action, action)];
+                }
+            }
+            let butterfly_value = butterfly_heuristic[action.from() as usize][action.to() as usize];
+            let capture_value = if action.is_capture() {
+                CAPTURE_VALUE
+            } else {
+                0
+            };
+            // Keeps the order of the generated actions
+            self.als[depth].swap(size, i);
+            self.values[depth][size] =
+                capture_value + (history_value as f32 / butterfly_value as f32) as u64;
+            size += 1;
+        }
+        self.als[depth].size = size;
+    }
+
+    // Has to be called when a quiet action caused a beta cutoff
+    pub fn update_quiet_cutoff(&mut self, color: usize, depth: usize, action: Action, bonus: u64) {
+        let previous_actions = self.previous_actions[depth];
+        if previous_actions[0] != Action::NONE {
+            self.counter_moves[color][previous_actions[0].piece() as usize]
+                [previous_actions[0].to() as usize] = action;
+        }
+        for (previous_action, continuation_history) in previous_actions
+            .iter()
+            .zip(self.continuation_history.iter_mut())
+        {
+            if *previous_action != Action::NONE {
+                continuation_history[continuation_index(*previous_action, action)] += bonus;
+            }
+        }
+    }
+
+    // Reduces the weight of the continuation history of earlier searches
+    pub fn age(&mut self) {
+        for continuation_history in self.continuation_history.iter_mut() {
+            for value in continuation_history.iter_mut() {
+                *value /= 8;
+            }
+        }
+    }
+
+    pub fn generate_tactical_moves(&mut self, state: &GameState, depth: usize) {
+        let color = state.get_current_color();
+        gamerules::get_tactical_actions(state, &mut self.als[depth]);
         for i in 0..self.als[depth].size {
             let action = self.als[depth][i];
-            let value = if action == pv_action {
-                PV_ACTION_VALUE
-            } else if action == tt_action {
-                TT_ACTION_VALUE
-            } else if action == killer_heuristic[0] || action == killer_heuristic[1] {
-                KILLER_MOVE_VALUE
+            self.values[depth][i] = if is_amber_action(action, color) {
+                AMBER_CAPUTURE_VALUE
             } else {
-                let history_value = history_heuristic[action.to() as usize][action.from() as usize];
-                let butterfly_value =
-                    butterfly_heuristic[action.to() as usize][action.from() as usize];
-                let capture_value = if action.is_amber_capture() || action.is_promotion(color) {
-                    AMBER_CAPUTURE_VALUE
-                } else if action.is_capture() {
-                    CAPTURE_VALUE
-                } else {
-                    0
-                };
-                capture_value + (history_value as f32 / butterfly_value as f32) as u64
+                CAPTURE_VALUE
             };
-            self.values[depth][i] = value;
         }
     }
 
+    // Returns the best remaining action after generate_tactical_moves
     pub fn next(&mut self, depth: usize) -> Action {
         if self.als[depth].size == 0 {
             return Action::NONE;
         }
+        self.pick_best(depth).0
+    }
+
+    fn pick_best(&mut self, depth: usize) -> (Action, u64) {
         let mut best_index = 0;
         let mut best_value = 0;
         for i in 0..self.als[depth].size {
@@ -79,7 +310,7 @@ impl MoveOrderer {
         }
         let next_action = self.als[depth][best_index];
         self.remove_action(depth, best_index);
-        next_action
+        (next_action, best_value)
     }
 
     pub fn remove_action(&mut self, depth: usize, index: usize) {
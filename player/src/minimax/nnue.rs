@@ -0,0 +1,269 @@
+use super::evaluation::MATE_VALUE;
+use game_sdk::gamestate::{GameState, BLUE, COLORS};
+use std::convert::TryInto;
+use std::fs;
+use std::io::{self, Error, ErrorKind};
+
+// Input features:
+// color * 512 + piece * 128 + stacked * 64 + square for every piece on the board
+// 1024 + color * 2 + (ambers - 1) for the first two ambers of each player
+// 1028 if it is blues turn
+pub const PIECE_FEATURES: usize = 1024;
+pub const AMBER_FEATURES: usize = 4;
+pub const INPUTS: usize = PIECE_FEATURES + AMBER_FEATURES + 1;
+pub const HIDDEN: usize = 128;
+pub const L2: usize = 32;
+
+const MAGIC: &[u8; 4] = b"SCNN";
+const ACTIVATION_MAX: i32 = 127;
+const L2_SHIFT: u32 = 6;
+const OUTPUT_SCALE: i32 = 16;
+
+// All weights are stored as little endian integers in the following order:
+// MAGIC, l1_weights (INPUTS * HIDDEN i16), l1_biases (HIDDEN i16),
+// l2_weights (L2 * HIDDEN i8), l2_biases (L2 i32), output_weights (L2 i8), output_bias (i32)
+pub const FILE_SIZE: usize = 4 + INPUTS * HIDDEN * 2 + HIDDEN * 2 + L2 * HIDDEN + L2 * 4 + L2 + 4;
+
+pub struct NeuralNetwork {
+    l1_weights: Vec<[i16; HIDDEN]>,
+    l1_biases: [i16; HIDDEN],
+    l2_weights: Vec<[i8; HIDDEN]>,
+    l2_biases: [i32; L2],
+    output_weights: [i8; L2],
+    output_bias: i32,
+}
+
+// The part of the game state that is visible to the network
+#[derive(Clone, Copy, Default)]
+struct Features {
+    pieces: [[[u64; 2]; 4]; 2],
+    ambers: [u8; 2],
+    color: usize,
+}
+
+impl Features {
+    fn from_state(state: &GameState) -> Self {
+        let mut features = Self {
+            ambers: [state.ambers[0].min(2), state.ambers[1].min(2)],
+            color: state.get_current_color(),
+            ..Self::default()
+        };
+        for color in COLORS {
+            for piece in 0..4 {
+                let board = state.board[color][piece];
+                features.pieces[color][piece] = [board & !state.stacked, board & state.stacked];
+            }
+        }
+        features
+    }
+}
+
+#[inline(always)]
+fn piece_feature(color: usize, piece: usize, stacked: usize, square: u32) -> usize {
+    color * 512 + piece * 128 + stacked * 64 + square as usize
+}
+
+#[inline(always)]
+fn amber_feature(color: usize, amber: u8) -> usize {
+    PIECE_FEATURES + color * 2 + amber as usize - 1
+}
+
+const SIDE_TO_MOVE_FEATURE: usize = PIECE_FEATURES + AMBER_FEATURES;
+
+#[derive(Clone, Copy)]
+pub struct Accumulator {
+    pub values: [i16; HIDDEN],
+}
+
+#[derive(Clone)]
+pub struct AccumulatorStack {
+    accumulators: Vec<(Accumulator, Features)>,
+}
+
+impl Default for AccumulatorStack {
+    fn default() -> Self {
+        Self {
+            accumulators: Vec::with_capacity(128),
+        }
+    }
+}
+
+impl AccumulatorStack {
+    pub fn reset(&mut self, network: &NeuralNetwork, state: &GameState) {
+        self.accumulators.clear();
+        self.accumulators
+            .push((network.refresh(state), Features::from_state(state)));
+    }
+
+    // Has to be called after every action that is done on the state
+    pub fn push(&mut self, network: &NeuralNetwork, state: &GameState) {
+        let (mut accumulator, old) = *self.accumulators.last().unwrap();
+        let new = Features::from_state(state);
+        network.update(&mut accumulator, &old, &new);
+        self.accumulators.push((accumulator, new));
+    }
+
+    // Has to be called after every action that is undone on the state
+    pub fn pop(&mut self) {
+        self.accumulators.pop();
+    }
+
+    pub fn top(&self) -> &Accumulator {
+        &self.accumulators.last().unwrap().0
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take<const N: usize>(&mut self) -> [u8; N] {
+        let bytes = self.bytes[self.position..self.position + N]
+            .try_into()
+            .unwrap();
+        self.position += N;
+        bytes
+    }
+
+    fn i8(&mut self) -> i8 {
+        i8::from_le_bytes(self.take())
+    }
+
+    fn i16(&mut self) -> i16 {
+        i16::from_le_bytes(self.take())
+    }
+
+    fn i32(&mut self) -> i32 {
+        i32::from_le_bytes(self.take())
+    }
+}
+
+impl NeuralNetwork {
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
+        if bytes.len() != FILE_SIZE || &bytes[0..4] != MAGIC {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "Invalid network file, expected {} bytes starting with {:?}",
+                    FILE_SIZE, MAGIC
+                ),
+            ));
+        }
+        let mut reader = Reader { bytes, position: 4 };
+        let mut l1_weights = vec![[0; HIDDEN]; INPUTS];
+        for weights in l1_weights.iter_mut() {
+            for weight in weights.iter_mut() {
+                *weight = reader.i16();
+            }
+        }
+        let mut l1_biases = [0; HIDDEN];
+        for bias in l1_biases.iter_mut() {
+            *bias = reader.i16();
+        }
+        let mut l2_weights = vec![[0; HIDDEN]; L2];
+        for weights in l2_weights.iter_mut() {
+            for weight in weights.iter_mut() {
+                *weight = reader.i8();
+            }
+        }
+        let mut l2_biases = [0; L2];
+        for bias in l2_biases.iter_mut() {
+            *bias = reader.i32();
+        }
+        let mut output_weights = [0; L2];
+        for weight in output_weights.iter_mut() {
+            *weight = reader.i8();
+        }
+        let output_bias = reader.i32();
+        Ok(Self {
+            l1_weights,
+            l1_biases,
+            l2_weights,
+            l2_biases,
+            output_weights,
+            output_bias,
+        })
+    }
+
+    pub fn load(path: &str) -> io::Result<Self> {
+        Self::from_bytes(&fs::read(path)?)
+    }
+
+    pub fn refresh(&self, state: &GameState) -> Accumulator {
+        let mut accumulator = Accumulator {
+            values: self.l1_biases,
+        };
+        let features = Features::from_state(state);
+        self.update(&mut accumulator, &Features::default(), &features);
+        accumulator
+    }
+
+    fn update(&self, accumulator: &mut Accumulator, old: &Features, new: &Features) {
+        for color in COLORS {
+            for piece in 0..4 {
+                for stacked in 0..2 {
+                    let old_pieces = old.pieces[color][piece][stacked];
+                    let new_pieces = new.pieces[color][piece][stacked];
+                    let mut removed = old_pieces & !new_pieces;
+                    while removed > 0 {
+                        let square = removed.trailing_zeros();
+                        removed ^= 1 << square;
+                        self.subtract(accumulator, piece_feature(color, piece, stacked, square));
+                    }
+                    let mut added = new_pieces & !old_pieces;
+                    while added > 0 {
+                        let square = added.trailing_zeros();
+                        added ^= 1 << square;
+                        self.add(accumulator, piece_feature(color, piece, stacked, square));
+                    }
+                }
+            }
+            for amber in (new.ambers[color] + 1)..=old.ambers[color] {
+                self.subtract(accumulator, amber_feature(color, amber));
+            }
+            for amber in (old.ambers[color] + 1)..=new.ambers[color] {
+                self.add(accumulator, amber_feature(color, amber));
+            }
+        }
+        if old.color != new.color {
+            if new.color == BLUE {
+                self.add(accumulator, SIDE_TO_MOVE_FEATURE);
+            } else {
+                self.subtract(accumulator, SIDE_TO_MOVE_FEATURE);
+            }
+        }
+    }
+
+    #[inline(always)]
+    fn add(&self, accumulator: &mut Accumulator, feature: usize) {
+        for (value, weight) in accumulator.values.iter_mut().zip(&self.l1_weights[feature]) {
+            *value = value.wrapping_add(*weight);
+        }
+    }
+
+    #[inline(always)]
+    fn subtract(&self, accumulator: &mut Accumulator, feature: usize) {
+        for (value, weight) in accumulator.values.iter_mut().zip(&self.l1_weights[feature]) {
+            *value = value.wrapping_sub(*weight);
+        }
+    }
+
+    // Returns the value of the position from the perspective of red
+    pub fn evaluate(&self, accumulator: &Accumulator) -> i16 {
+        let mut hidden = [0i32; HIDDEN];
+        for (activation, value) in hidden.iter_mut().zip(accumulator.values.iter()) {
+            *activation = (*value as i32).clamp(0, ACTIVATION_MAX);
+        }
+        let mut output = self.output_bias;
+        for i in 0..L2 {
+            let mut sum = self.l2_biases[i];
+            for (activation, weight) in hidden.iter().zip(self.l2_weights[i].iter()) {
+                sum += activation * *weight as i32;
+            }
+            output += (sum >> L2_SHIFT).clamp(0, ACTIVATION_MAX) * self.output_weights[i] as i32;
+        }
+        (output / OUTPUT_SCALE).clamp(-(MATE_VALUE as i32) + 1, MATE_VALUE as i32 - 1) as i16
+    }
+}
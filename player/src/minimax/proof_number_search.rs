@@ -0,0 +1,302 @@
+use game_sdk::action::{Action, ActionList};
+use game_sdk::gamerules;
+use game_sdk::gamestate::{GameState, BLUE, RED};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+pub const DEFAULT_MAX_NODES: usize = 1_000_000;
+// Positions are solved close to the end of the game, or a little earlier if only a few
+// pieces are left. With more plies left the solver runs out of nodes in most positions.
+pub const SOLVER_MAX_REMAINING_PLIES: u8 = 8;
+pub const SOLVER_MAX_PIECES: u32 = 6;
+pub const SOLVER_MAX_REMAINING_PLIES_WITH_FEW_PIECES: u8 = 10;
+// Share of the move time in percent that the solver may use before the search takes over
+pub const SOLVER_TIME_SHARE: u128 = 25;
+const INFINITY: u32 = u32::MAX;
+
+// Game-theoretic value of a position for the player to move
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ProofResult {
+    Win,
+    Draw,
+    Loss,
+    // The node budget or the time was exhausted before the value was proven
+    Unknown,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Proof {
+    pub result: ProofResult,
+    // One line of the proof tree, starting with an action that keeps the result. Empty if
+    // the result is a loss or unknown.
+    pub pv: Vec<Action>,
+    // Number of nodes that were created by both searches
+    pub nodes: usize,
+}
+
+impl Proof {
+    // The proven action, if the player to move can enforce a win or a draw
+    pub fn best_action(&self) -> Option<Action> {
+        match self.result {
+            ProofResult::Win | ProofResult::Draw => self.pv.first().copied(),
+            _ => None,
+        }
+    }
+}
+
+// Whether the position is close enough to the end of the game to try to solve it
+pub fn is_solvable(state: &GameState) -> bool {
+    let remaining_plies = 59u8.saturating_sub(state.ply);
+    let pieces = (state.occupied[RED] | state.occupied[BLUE]).count_ones();
+    remaining_plies <= SOLVER_MAX_REMAINING_PLIES
+        || (pieces <= SOLVER_MAX_PIECES
+            && remaining_plies <= SOLVER_MAX_REMAINING_PLIES_WITH_FEW_PIECES)
+}
+
+#[derive(Clone, Copy)]
+struct Node {
+    action: Action,
+    parent: usize,
+    proof: u32,
+    disproof: u32,
+    children_start: usize,
+    children_count: u16,
+    is_expanded: bool,
+}
+
+impl Node {
+    fn new(action: Action, parent: usize) -> Self {
+        Self {
+            action,
+            parent,
+            proof: 1,
+            disproof: 1,
+            children_start: 0,
+            children_count: 0,
+            is_expanded: false,
+        }
+    }
+
+    fn children(&self) -> std::ops::Range<usize> {
+        self.children_start..self.children_start + self.children_count as usize
+    }
+
+    fn set_proven(&mut self, is_proven: bool) {
+        if is_proven {
+            self.proof = 0;
+            self.disproof = INFINITY;
+        } else {
+            self.proof = INFINITY;
+            self.disproof = 0;
+        }
+    }
+}
+
+// Proves the result of positions close to the end of the game with proof-number search.
+// Every search proves or disproves a single goal for the player to move, so the solver
+// first tries to prove a win and then tries to prove at least a draw.
+pub struct ProofNumberSearch {
+    // Maximum number of nodes of each of the two searches
+    pub max_nodes: usize,
+    // Time limit in milliseconds for both searches, None to only stop at max_nodes
+    pub time_limit: Option<u128>,
+    // Stops both searches when it is set, e.g. by the stop handle of the searcher
+    pub stop_signal: Option<Arc<AtomicBool>>,
+    start_time: Instant,
+    nodes: Vec<Node>,
+    al: ActionList,
+}
+
+impl Default for ProofNumberSearch {
+    fn default() -> Self {
+        Self {
+            max_nodes: DEFAULT_MAX_NODES,
+            time_limit: None,
+            stop_signal: None,
+            start_time: Instant::now(),
+            nodes: Vec::new(),
+            al: ActionList::default(),
+        }
+    }
+}
+
+impl ProofNumberSearch {
+    pub fn solve(&mut self, state: &GameState) -> Proof {
+        self.start_time = Instant::now();
+        let mut nodes = 0;
+        for (result, minimum_result) in [(ProofResult::Win, 1), (ProofResult::Draw, 0)] {
+            let is_proven = self.search(state, minimum_result);
+            nodes += self.nodes.len();
+            match is_proven {
+                Some(true) => {
+                    return Proof {
+                        result,
+                        pv: self.principal_variation(),
+                        nodes,
+                    }
+                }
+                Some(false) => continue,
+                None => {
+                    return Proof {
+                        result: ProofResult::Unknown,
+                        pv: Vec::new(),
+                        nodes,
+                    }
+                }
+            }
+        }
+        Proof {
+            result: ProofResult::Loss,
+            pv: Vec::new(),
+            nodes,
+        }
+    }
+
+    // Tries to prove that the player to move can reach a game result of at least
+    // minimum_result, from the perspective of the player to move. Returns None if the
+    // node budget or the time is exhausted or if the search was stopped.
+    fn search(&mut self, root_state: &GameState, minimum_result: i16) -> Option<bool> {
+        let color = root_state.get_current_color();
+        self.nodes.clear();
+        self.nodes.push(Node::new(Action::NONE, 0));
+        if let Some(is_proven) = Self::evaluate(root_state, color, minimum_result) {
+            self.nodes[0].set_proven(is_proven);
+        }
+        let mut iterations: usize = 0;
+        while self.nodes[0].proof != 0 && self.nodes[0].disproof != 0 {
+            if self.nodes.len() >= self.max_nodes {
+                return None;
+            }
+            iterations += 1;
+            if iterations.is_multiple_of(256) && self.should_stop() {
+                return None;
+            }
+            let mut state = root_state.clone();
+            let (node, distance) = self.select_most_proving_node(&mut state, color);
+            self.expand(node, &mut state, color, minimum_result);
+            self.update_ancestors(node, distance);
+        }
+        Some(self.nodes[0].proof == 0)
+    }
+
+    fn should_stop(&self) -> bool {
+        matches!(self.time_limit, Some(time_limit) if self.start_time.elapsed().as_millis() >= time_limit)
+            || matches!(&self.stop_signal, Some(stop_signal) if stop_signal.load(Ordering::Relaxed))
+    }
+
+    // Proven if the game is over with a result of at least minimum_result for color,
+    // disproven if it is over with a worse result, None if it is not over
+    fn evaluate(state: &GameState, color: usize, minimum_result: i16) -> Option<bool> {
+        if !gamerules::is_game_over(state) {
+            return None;
+        }
+        let color_sign = if color == 0 { 1 } else { -1 };
+        Some(gamerules::game_result(state) * color_sign >= minimum_result)
+    }
+
+    // The attacker chooses the child that is the easiest to prove and the defender
+    // chooses the child that is the easiest to disprove. Returns the node and its distance
+    // to the root.
+    fn select_most_proving_node(&self, state: &mut GameState, color: usize) -> (usize, usize) {
+        let mut node = 0;
+        let mut distance = 0;
+        while self.nodes[node].is_expanded {
+            let is_attacker = state.get_current_color() == color;
+            node = self.nodes[node]
+                .children()
+                .min_by_key(|&child| {
+                    if is_attacker {
+                        self.nodes[child].proof
+                    } else {
+                        self.nodes[child].disproof
+                    }
+                })
+                .unwrap();
+            gamerules::do_action(state, self.nodes[node].action);
+            distance += 1;
+        }
+        (node, distance)
+    }
+
+    fn expand(&mut self, node: usize, state: &mut GameState, color: usize, minimum_result: i16) {
+        gamerules::get_legal_actions(state, &mut self.al);
+        self.nodes[node].is_expanded = true;
+        if self.al.size == 0 {
+            // Like in the search, the player without legal actions wins
+            let is_attacker = state.get_current_color() == color;
+            self.nodes[node].set_proven(is_attacker);
+            return;
+        }
+        self.nodes[node].children_start = self.nodes.len();
+        self.nodes[node].children_count = self.al.size as u16;
+        for i in 0..self.al.size {
+            let action = self.al[i];
+            let mut child = Node::new(action, node);
+            gamerules::do_action(state, action);
+            if let Some(is_proven) = Self::evaluate(state, color, minimum_result) {
+                child.set_proven(is_proven);
+            }
+            gamerules::undo_action(state, action);
+            self.nodes.push(child);
+        }
+    }
+
+    fn update_ancestors(&mut self, mut node: usize, mut distance: usize) {
+        loop {
+            if self.nodes[node].children_count > 0 {
+                // The attacker moves at nodes with an even distance to the root
+                let (proof, disproof) = self.combine_children(node, distance.is_multiple_of(2));
+                self.nodes[node].proof = proof;
+                self.nodes[node].disproof = disproof;
+            }
+            if node == 0 {
+                break;
+            }
+            node = self.nodes[node].parent;
+            distance -= 1;
+        }
+    }
+
+    // At attacker nodes one proven child proves the node and all children have to be
+    // disproven to disprove it. At defender nodes it is the other way around.
+    fn combine_children(&self, node: usize, is_attacker: bool) -> (u32, u32) {
+        let mut minimum = INFINITY;
+        let mut sum: u32 = 0;
+        for child in self.nodes[node].children() {
+            let (minimized, summed) = if is_attacker {
+                (self.nodes[child].proof, self.nodes[child].disproof)
+            } else {
+                (self.nodes[child].disproof, self.nodes[child].proof)
+            };
+            minimum = minimum.min(minimized);
+            sum = sum.saturating_add(summed);
+        }
+        if is_attacker {
+            (minimum, sum)
+        } else {
+            (sum, minimum)
+        }
+    }
+
+    // Follows proven children at attacker nodes and the first child at defender nodes,
+    // where every child is proven
+    fn principal_variation(&self) -> Vec<Action> {
+        let mut pv = Vec::new();
+        let mut node = 0;
+        let mut is_attacker = true;
+        while self.nodes[node].children_count > 0 {
+            node = if is_attacker {
+                self.nodes[node]
+                    .children()
+                    .find(|&child| self.nodes[child].proof == 0)
+                    .unwrap()
+            } else {
+                self.nodes[node].children_start
+            };
+            pv.push(self.nodes[node].action);
+            is_attacker = !is_attacker;
+        }
+        pv
+    }
+}
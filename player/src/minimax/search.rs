@@ -1,18 +1,35 @@
+use super::attacks::AttackStack;
 use super::cache::{TranspositionTable, TranspositionTableEntry};
-use super::evaluation::{static_evaluation, MATE_VALUE};
-use super::move_ordering::MoveOrderer;
+use super::evaluation::MATE_VALUE;
+use super::evaluator::{Evaluator, StaticEvaluator};
+use super::limits::SearchLimits;
+use super::move_ordering::{MoveOrderer, KILLER_MOVE_VALUE};
+use super::proof_number_search::{
+    is_solvable, ProofNumberSearch, ProofResult, DEFAULT_MAX_NODES, SOLVER_TIME_SHARE,
+};
+use super::search_info::{SearchInfo, SearchObserver, TablePrinter};
+use super::selectivity::*;
+use super::time_manager::TimeManager;
 use game_sdk::action::*;
 use game_sdk::gamerules;
 use game_sdk::gamestate::*;
 use game_sdk::player::Player;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
 use std::time::Instant;
 
-pub const MAX_VALUE: i16 = std::i16::MAX;
+pub const MAX_VALUE: i16 = i16::MAX;
 pub const MIN_VALUE: i16 = -MAX_VALUE;
 pub const MAX_SEARCH_DEPTH: usize = 60;
-pub const STANDARD_VALUE: i16 = std::i16::MIN + 1;
+pub const STANDARD_VALUE: i16 = i16::MIN + 1;
+// The first iterations are searched with the full window because their values are unstable
+pub const ASPIRATION_MIN_DEPTH: usize = 4;
+pub const ASPIRATION_WINDOW: i16 = 100;
+// Failing sides of windows that would grow beyond this are opened completely
+pub const ASPIRATION_MAX_WINDOW: i16 = 200;
 
-pub struct Searcher {
+pub struct Searcher<E: Evaluator = StaticEvaluator> {
     pub stop: bool,
     pub nodes_searched: usize,
     pub move_orderer: MoveOrderer,
@@ -22,41 +39,59 @@ pub struct Searcher {
     pub history_heuristic: [[[u64; 64]; 64]; 2],
     pub butterfly_heuristic: [[[u64; 64]; 64]; 2],
     pub killer_heuristic: [[Action; 2]; MAX_SEARCH_DEPTH],
-    //pub counter_move_heuristic: [[Action; 64]; 64],
+    pub action_stack: [Action; MAX_SEARCH_DEPTH],
+    // Number of threat extensions on the line to each node
+    extension_stack: [usize; MAX_SEARCH_DEPTH],
+    // Attacks of every position on the search path, for the threat extensions
+    attacks: AttackStack,
+    excluded_root_actions: ActionList,
     pub start_time: Instant,
-    pub time_limit: u128,
-    pub tt: TranspositionTable,
+    pub limits: SearchLimits,
+    // Stops the search before the time limit if another iteration is not worth it
+    pub time_management: bool,
+    pub observers: Vec<Box<dyn SearchObserver>>,
+    pub best_value: i16,
+    pub tt: Arc<TranspositionTable>,
+    pub use_tt_cutoffs: bool,
+    // Searches the root with a window around the value of the previous iteration
+    pub aspiration_windows: bool,
+    pub selective_search: SelectiveSearch,
+    // Tries to prove the result of positions close to the end of the game before searching
+    pub endgame_solver: bool,
+    pub solver: ProofNumberSearch,
+    pub evaluator: E,
     //pub evaluation_cache: EvaluationCache,
+    pub threads: usize,
+    helpers: Vec<Searcher<E>>,
+    stop_signal: Arc<AtomicBool>,
 }
 
 impl Default for Searcher {
     fn default() -> Self {
-        Self {
-            stop: false,
-            nodes_searched: 0,
-            move_orderer: MoveOrderer::default(),
-            pv: ActionList::default(),
-            pv_table: ActionListStack::with_size(MAX_SEARCH_DEPTH),
-            pv_hash_table: Vec::with_capacity(MAX_SEARCH_DEPTH),
-            history_heuristic: [[[0; 64]; 64]; 2],
-            butterfly_heuristic: [[[1; 64]; 64]; 2],
-            killer_heuristic: [[Action::NONE; 2]; MAX_SEARCH_DEPTH],
-            //counter_move_heuristic: [[Action::NONE; 64]; 64],
-            start_time: Instant::now(),
-            time_limit: 1970,
-            tt: TranspositionTable::default(),
-            //evaluation_cache: EvaluationCache::default(),
-        }
+        Self::with_evaluator(StaticEvaluator::default())
     }
 }
 
-impl Player for Searcher {
+impl<E: Evaluator> Player for Searcher<E> {
     fn on_move_request(&mut self, state: &GameState) -> Action {
         self.search(state)
     }
 
     fn set_time_limit(&mut self, time_limit: u64) {
-        self.time_limit = time_limit as u128
+        self.limits = SearchLimits::movetime(time_limit as u128)
+    }
+
+    fn set_threads(&mut self, threads: usize) {
+        self.threads = threads.max(1);
+        self.helpers = (1..self.threads)
+            .map(|_| {
+                Searcher::new(
+                    Arc::clone(&self.tt),
+                    Arc::clone(&self.stop_signal),
+                    self.evaluator.clone(),
+                )
+            })
+            .collect();
     }
 
     fn reset(&mut self) {
@@ -69,22 +104,169 @@ impl Player for Searcher {
         self.history_heuristic = [[[0; 64]; 64]; 2];
         self.butterfly_heuristic = [[[1; 64]; 64]; 2];
         self.killer_heuristic = [[Action::NONE; 2]; MAX_SEARCH_DEPTH];
-        //self.counter_move_heuristic = [[Action::NONE; 64]; 64];
-        self.tt = TranspositionTable::default();
+        self.tt.clear();
         //self.evaluation_cache = EvaluationCache::default();
+        self.set_threads(self.threads);
+    }
+}
+
+// Widens the failing side of an aspiration window by delta. Returns None if the value lies
+// inside of the window. Mates are far outside of any window, so they would need many
+// re-searches to be found with a window that grows step by step.
+pub fn widen_aspiration_window(
+    value: i16,
+    alpha: i16,
+    beta: i16,
+    delta: i16,
+) -> Option<(i16, i16)> {
+    if value <= alpha && alpha > MIN_VALUE {
+        if delta > ASPIRATION_MAX_WINDOW {
+            Some((MIN_VALUE, beta))
+        } else {
+            Some((value.saturating_sub(delta), beta))
+        }
+    } else if value >= beta && beta < MAX_VALUE {
+        if delta > ASPIRATION_MAX_WINDOW {
+            Some((alpha, MAX_VALUE))
+        } else {
+            Some((alpha, value.saturating_add(delta)))
+        }
+    } else {
+        None
+    }
+}
+
+// Mate values are stored relative to the node instead of the root, so that they stay
+// correct when the position is reached at a different distance from the root.
+#[inline(always)]
+fn value_to_tt(value: i16, depth: usize) -> i16 {
+    if value >= MATE_VALUE {
+        value + depth as i16
+    } else if value <= -MATE_VALUE {
+        value - depth as i16
+    } else {
+        value
+    }
+}
+
+#[inline(always)]
+fn value_from_tt(value: i16, depth: usize) -> i16 {
+    if value >= MATE_VALUE {
+        value - depth as i16
+    } else if value <= -MATE_VALUE {
+        value + depth as i16
+    } else {
+        value
     }
 }
 
-impl Searcher {
+impl<E: Evaluator> Searcher<E> {
+    pub fn with_evaluator(evaluator: E) -> Self {
+        let mut searcher = Self::new(
+            Arc::new(TranspositionTable::default()),
+            Arc::new(AtomicBool::new(false)),
+            evaluator,
+        );
+        searcher.observers.push(Box::new(TablePrinter));
+        searcher
+    }
+
+    fn new(tt: Arc<TranspositionTable>, stop_signal: Arc<AtomicBool>, evaluator: E) -> Self {
+        Self {
+            stop: false,
+            nodes_searched: 0,
+            move_orderer: MoveOrderer::default(),
+            pv: ActionList::default(),
+            pv_table: ActionListStack::with_size(MAX_SEARCH_DEPTH),
+            pv_hash_table: Vec::with_capacity(MAX_SEARCH_DEPTH),
+            history_heuristic: [[[0; 64]; 64]; 2],
+            butterfly_heuristic: [[[1; 64]; 64]; 2],
+            killer_heuristic: [[Action::NONE; 2]; MAX_SEARCH_DEPTH],
+            action_stack: [Action::NONE; MAX_SEARCH_DEPTH],
+            extension_stack: [0; MAX_SEARCH_DEPTH],
+            attacks: AttackStack::default(),
+            excluded_root_actions: ActionList::default(),
+            start_time: Instant::now(),
+            limits: SearchLimits::default(),
+            time_management: true,
+            observers: Vec::new(),
+            best_value: 0,
+            tt,
+            use_tt_cutoffs: true,
+            aspiration_windows: true,
+            selective_search: SelectiveSearch::default(),
+            endgame_solver: true,
+            solver: ProofNumberSearch::default(),
+            evaluator,
+            //evaluation_cache: EvaluationCache::default(),
+            threads: 1,
+            helpers: Vec::new(),
+            stop_signal,
+        }
+    }
+
+    // Setting the stop handle aborts the current search. The search then returns the best
+    // action of the last completed iteration.
+    pub fn stop_handle(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.stop_signal)
+    }
+
+    // Replaces the transposition table by an empty one of the given size
+    pub fn set_hash_size(&mut self, megabytes: usize) {
+        self.tt = Arc::new(TranspositionTable::with_size(megabytes));
+        self.set_threads(self.threads);
+    }
+
+    pub fn set_stop_handle(&mut self, stop_handle: Arc<AtomicBool>) {
+        self.stop_signal = stop_handle;
+        self.set_threads(self.threads);
+    }
+
     pub fn search(&mut self, state: &GameState) -> Action {
-        println!("Searching action using PV-Search for {}", state.to_fen());
-        println!("Depth  Value     Nodes     Elapsed   Nodes/s PV");
-        let mut state = state.clone();
+        self.run_search(state, |searcher| searcher.main_search(state))
+    }
+
+    // Searches the best root actions with their own values and principal variations.
+    // Every line is searched with all root actions of the better lines excluded.
+    pub fn analyze(&mut self, state: &GameState, lines: usize) -> Vec<SearchInfo> {
+        self.run_search(state, |searcher| searcher.multi_pv_search(state, lines))
+    }
+
+    fn run_search<T>(&mut self, state: &GameState, search: impl FnOnce(&mut Self) -> T) -> T {
+        for observer in self.observers.iter_mut() {
+            observer.on_search_start(state);
+        }
         self.start_time = Instant::now();
+        self.stop_signal.store(false, Ordering::Relaxed);
+        self.tt.new_search();
+        // Lazy SMP: The helper threads search the same position and only communicate
+        // with the main thread through the shared transposition table.
+        let mut helpers = std::mem::take(&mut self.helpers);
+        let result = thread::scope(|scope| {
+            for (thread_index, helper) in helpers.iter_mut().enumerate() {
+                helper.start_time = self.start_time;
+                helper.limits = self.limits;
+                helper.use_tt_cutoffs = self.use_tt_cutoffs;
+                helper.selective_search = self.selective_search;
+                helper.evaluator = self.evaluator.clone();
+                scope.spawn(move || helper.helper_search(state, thread_index + 1));
+            }
+            let result = search(self);
+            self.stop_signal.store(true, Ordering::Relaxed);
+            result
+        });
+        self.helpers = helpers;
+        result
+    }
+
+    pub(crate) fn prepare_search(&mut self, state: &GameState) {
+        self.evaluator.reset(state);
+        self.attacks.reset(state);
         self.nodes_searched = 0;
         self.stop = false;
         self.pv.clear();
         self.pv_hash_table.clear();
+        self.move_orderer.age();
         for i in 0..2 {
             for j in 0..64 {
                 for k in 0..64 {
@@ -94,44 +276,59 @@ impl Searcher {
                 }
             }
         }
+    }
+
+    fn main_search(&mut self, state: &GameState) -> Action {
+        let mut state = state.clone();
+        self.prepare_search(&state);
         let mut best_action = Action::NONE;
-        for depth in 1..=MAX_SEARCH_DEPTH {
-            let current_value = self.pv_search(&mut state, 0, depth, MIN_VALUE, MAX_VALUE);
-            let elapsed = Instant::now().duration_since(self.start_time).as_micros();
-            let nps = self.nodes_searched as f64 / (elapsed as f64 / 1_000_000.0);
-            print!(
-                "{:5} {:6} {:9} {:9}μs {:9.0} ",
-                depth, current_value, self.nodes_searched, elapsed, nps
-            );
+        let mut last_info = None;
+        self.best_value = 0;
+        // Searches with a depth limit are expected to search to that depth
+        let is_depth_limited = self.limits.max_depth < MAX_SEARCH_DEPTH;
+        if self.endgame_solver && !is_depth_limited && is_solvable(&state) {
+            if let Some(action) = self.solve_endgame(&state) {
+                return action;
+            }
+        }
+        let mut time_manager = match self.limits.movetime {
+            Some(movetime) if self.time_management => {
+                gamerules::get_legal_actions(&state, &mut self.pv);
+                Some(TimeManager::new(&state, movetime, self.pv.size))
+            }
+            _ => None,
+        };
+        for depth in 1..=self.limits.max_depth.min(MAX_SEARCH_DEPTH) {
+            let current_value = if self.aspiration_windows && depth >= ASPIRATION_MIN_DEPTH {
+                self.aspiration_search(&mut state, depth, self.best_value)
+            } else {
+                self.pv_search(&mut state, 0, depth, MIN_VALUE, MAX_VALUE)
+            };
+            if !self.stop {
+                self.pv = self.pv_table[0].clone();
+            }
+            let info = self.search_info(depth, current_value);
+            for observer in self.observers.iter_mut() {
+                observer.on_iteration(&info);
+            }
             if self.stop {
-                println!("(canceled)");
                 break;
             }
             let mut toy_state = state.clone();
-            self.pv = self.pv_table[0].clone();
             if self.pv.size != 0 {
                 best_action = self.pv[0];
+                self.best_value = current_value;
+                last_info = Some(info);
             }
-            println!("{}", self.format_pv());
-            if self.pv.size != depth {
-                println!("Reached the end of the search tree.");
-                if current_value >= MATE_VALUE {
-                    println!(
-                        "Mate in {}. Value: +{}",
-                        depth - 1,
-                        current_value - MATE_VALUE
-                    );
-                } else if current_value == 0 {
-                    println!("Draw in {}", depth - 1);
-                } else {
-                    println!(
-                        "Mated in {} Value: {}",
-                        depth - 1,
-                        current_value + MATE_VALUE
-                    );
-                }
+            if self.pv.size < depth {
                 break;
             }
+            if let Some(time_manager) = time_manager.as_mut() {
+                let elapsed = self.start_time.elapsed().as_millis();
+                if time_manager.should_stop(elapsed, best_action, current_value) {
+                    break;
+                }
+            }
             self.pv_hash_table.clear();
             for i in 0..self.pv.size {
                 self.pv_hash_table.push(toy_state.hash as usize);
@@ -141,37 +338,214 @@ impl Searcher {
         if best_action == Action::NONE {
             gamerules::get_legal_actions(&state, &mut self.pv);
             best_action = self.pv[0];
-            println!("No move found.");
+        }
+        for observer in self.observers.iter_mut() {
+            observer.on_search_end(best_action, last_info.as_ref());
         }
         best_action
     }
 
-    fn format_pv(&self) -> String {
-        let mut s = String::new();
-        let mut line_length = 0;
-        for i in 0..self.pv.size {
-            let next_action = &format!("{} ", self.pv[i]);
-            let len = next_action.len();
-            if line_length + len > 100 {
-                s.push_str("\n    .      .         .           .         . ");
-                line_length = 0;
+    // Returns the proven action if the solver proves that the player to move can enforce
+    // a win or a draw
+    fn solve_endgame(&mut self, state: &GameState) -> Option<Action> {
+        self.solver.time_limit = self
+            .limits
+            .movetime
+            .map(|movetime| movetime * SOLVER_TIME_SHARE / 100);
+        // Both searches of the solver together stay within the node limit
+        self.solver.max_nodes = DEFAULT_MAX_NODES.min(self.limits.max_nodes / 2);
+        self.solver.stop_signal = Some(Arc::clone(&self.stop_signal));
+        let proof = self.solver.solve(state);
+        let action = proof.best_action()?;
+        // The proof doesn't tell how fast the opponent can be beaten, only that the game is
+        // won at its end at the latest
+        let value = if proof.result == ProofResult::Win {
+            MATE_VALUE + MAX_SEARCH_DEPTH as i16 - (59 - state.ply as i16).max(1)
+        } else {
+            0
+        };
+        self.nodes_searched = proof.nodes;
+        self.best_value = value;
+        self.pv.clear();
+        for action in proof.pv {
+            self.pv.push(action);
+        }
+        let info = self.search_info(self.pv.size, value);
+        for observer in self.observers.iter_mut() {
+            observer.on_iteration(&info);
+            observer.on_search_end(action, Some(&info));
+        }
+        Some(action)
+    }
+
+    // Searches the root with a small window around the expected value. The window is
+    // widened on the failing side until the value lies inside of it.
+    pub fn aspiration_search(&mut self, state: &mut GameState, depth: usize, expected: i16) -> i16 {
+        if expected.abs() >= MATE_VALUE {
+            return self.pv_search(state, 0, depth, MIN_VALUE, MAX_VALUE);
+        }
+        let mut delta = ASPIRATION_WINDOW;
+        let mut alpha = expected - delta;
+        let mut beta = expected + delta;
+        loop {
+            let value = self.pv_search(state, 0, depth, alpha, beta);
+            if self.stop {
+                return value;
+            }
+            delta *= 2;
+            match widen_aspiration_window(value, alpha, beta, delta) {
+                Some(window) => (alpha, beta) = window,
+                None => return value,
             }
-            line_length += len;
-            s.push_str(next_action);
         }
-        s
     }
 
-    fn pv_search(
+    fn multi_pv_search(&mut self, state: &GameState, lines: usize) -> Vec<SearchInfo> {
+        let mut state = state.clone();
+        self.prepare_search(&state);
+        let mut results: Vec<SearchInfo> = Vec::new();
+        'deepening: for depth in 1..=self.limits.max_depth.min(MAX_SEARCH_DEPTH) {
+            let mut infos = Vec::with_capacity(lines);
+            self.excluded_root_actions.clear();
+            for _ in 0..lines {
+                let value = self.pv_search(&mut state, 0, depth, MIN_VALUE, MAX_VALUE);
+                if self.stop {
+                    break 'deepening;
+                }
+                if self.pv_table[0].size == 0 {
+                    // All root actions are excluded
+                    break;
+                }
+                self.pv = self.pv_table[0].clone();
+                self.excluded_root_actions.push(self.pv[0]);
+                infos.push(self.search_info(depth, value));
+            }
+            infos.sort_by_key(|info| -info.value);
+            for (i, info) in infos.iter_mut().enumerate() {
+                info.multi_pv = i + 1;
+            }
+            for info in infos.iter() {
+                for observer in self.observers.iter_mut() {
+                    observer.on_iteration(info);
+                }
+            }
+            let is_end_of_search_tree = infos.iter().all(|info| info.reached_end_of_search_tree());
+            results = infos;
+            if is_end_of_search_tree {
+                break;
+            }
+        }
+        self.excluded_root_actions.clear();
+        let best_action = if let Some(info) = results.first() {
+            self.best_value = info.value;
+            info.pv[0]
+        } else {
+            gamerules::get_legal_actions(&state, &mut self.pv);
+            if self.pv.size > 0 {
+                self.pv[0]
+            } else {
+                Action::NONE
+            }
+        };
+        for observer in self.observers.iter_mut() {
+            observer.on_search_end(best_action, results.first());
+        }
+        results
+    }
+
+    fn search_info(&self, depth: usize, value: i16) -> SearchInfo {
+        let elapsed = self.start_time.elapsed().as_micros();
+        let pv = if self.stop {
+            Vec::new()
+        } else {
+            (0..self.pv.size).map(|i| self.pv[i]).collect()
+        };
+        SearchInfo {
+            depth,
+            value,
+            mate_distance: SearchInfo::mate_distance(value),
+            nodes: self.nodes_searched,
+            nps: (self.nodes_searched as f64 / (elapsed as f64 / 1_000_000.0)).round(),
+            elapsed,
+            pv,
+            hashfull: self.tt.hashfull(),
+            canceled: self.stop,
+            multi_pv: 0,
+        }
+    }
+
+    fn helper_search(&mut self, state: &GameState, thread_index: usize) {
+        let mut state = state.clone();
+        self.prepare_search(&state);
+        // Every other helper skips the first iteration so that the threads don't search
+        // the same depth at the same time.
+        for depth in (1 + thread_index % 2)..=MAX_SEARCH_DEPTH {
+            self.pv_search(&mut state, 0, depth, MIN_VALUE, MAX_VALUE);
+            if self.stop || self.pv_table[0].size < depth {
+                break;
+            }
+        }
+    }
+
+    #[inline(always)]
+    fn evaluate(&self, state: &GameState) -> i16 {
+        self.evaluator.evaluate(state)
+    }
+
+    #[inline(always)]
+    fn do_action(&mut self, state: &mut GameState, action: Action) {
+        gamerules::do_action(state, action);
+        self.evaluator.push(state, action);
+        self.attacks.push(state, action);
+    }
+
+    #[inline(always)]
+    fn undo_action(&mut self, state: &mut GameState, action: Action) {
+        gamerules::undo_action(state, action);
+        self.evaluator.pop();
+        self.attacks.pop();
+    }
+
+    #[inline(always)]
+    fn do_null_action(&mut self, state: &mut GameState) {
+        gamerules::do_null_action(state);
+        self.evaluator.push_null(state);
+        self.attacks.push_null();
+    }
+
+    #[inline(always)]
+    fn undo_null_action(&mut self, state: &mut GameState) {
+        gamerules::undo_null_action(state);
+        self.evaluator.pop();
+        self.attacks.pop();
+    }
+
+    #[inline(always)]
+    fn check_stop(&mut self) {
+        // Nodes that are visited after the search was stopped are not counted
+        if self.stop {
+            return;
+        }
+        self.nodes_searched += 1;
+        if self.nodes_searched >= self.limits.max_nodes {
+            self.stop = true;
+        } else if self.nodes_searched.is_multiple_of(2048) {
+            self.stop = self
+                .limits
+                .is_time_exceeded(self.start_time.elapsed().as_millis())
+                || self.stop_signal.load(Ordering::Relaxed);
+        }
+    }
+
+    pub(crate) fn pv_search(
         &mut self,
         state: &mut GameState,
         depth: usize,
-        depth_left: usize,
+        mut depth_left: usize,
         mut alpha: i16,
         beta: i16,
     ) -> i16 {
         self.pv_table[depth].clear();
-        self.nodes_searched += 1;
         let is_pv_node = beta > 1 + alpha;
         //let is_root_node = depth == 0;
         let is_game_over = gamerules::is_game_over(state);
@@ -183,24 +557,43 @@ impl Searcher {
             0 => 1,
             _ => -1,
         };
-        if self.nodes_searched % 2048 == 0 {
-            self.stop = self.start_time.elapsed().as_millis() >= self.time_limit;
-        }
+        self.check_stop();
 
         if is_game_over {
             let result = gamerules::game_result(state);
             return (MATE_VALUE + MAX_SEARCH_DEPTH as i16 - depth as i16) * color_sign * result;
         }
 
-        if depth_left == 0 || self.stop {
+        if self.stop {
             /*return if let Some(value) = self.evaluation_cache.lookup(hash) {
                 value
             } else {
-                let value = static_evaluation(state);
+                let value = static_evaluation(state, &self.evaluation_parameters);
                 self.evaluation_cache.insert(hash, value);
                 value
             } * color_sign;*/
-            return static_evaluation(state) * color_sign;
+            return self.evaluate(state) * color_sign;
+        }
+
+        // Threat extensions: Lines in which the opponent threatens to win an amber with the
+        // next action are searched one ply deeper, so that amber races are resolved by the
+        // search and not by the evaluation.
+        let mut extensions = if depth > 0 {
+            self.extension_stack[depth - 1]
+        } else {
+            0
+        };
+        let is_extended = self.selective_search.threat_extensions
+            && extensions < MAX_THREAT_EXTENSIONS
+            && has_amber_threat(state, self.attacks.top());
+        if is_extended {
+            extensions += 1;
+            depth_left += 1;
+        }
+        self.extension_stack[depth] = extensions;
+
+        if depth_left == 0 {
+            return self.quiescence_search(state, depth, alpha, beta);
         }
 
         let pv_action = if self.pv_table[depth].size > 0 && hash == self.pv_hash_table[depth] {
@@ -210,44 +603,157 @@ impl Searcher {
         };
 
         let tt_action = if let Some(entry) = self.tt.lookup(hash) {
-            // TODO:
+            // Cutoffs are only taken in non PV nodes so that the PV stays intact
+            if self.use_tt_cutoffs && !is_pv_node && entry.depth as usize >= depth_left {
+                let value = value_from_tt(entry.value, depth);
+                if entry.beta {
+                    if value >= beta {
+                        return value;
+                    }
+                } else if entry.alpha {
+                    if value <= alpha {
+                        return value;
+                    }
+                } else {
+                    return value;
+                }
+            }
             entry.action
         } else {
             Action::NONE
         };
 
-        self.move_orderer.generate_moves(
+        let static_value = if !is_pv_node && self.selective_search.is_enabled() {
+            self.evaluate(state) * color_sign
+        } else {
+            0
+        };
+        let is_static_value_reliable = !is_pv_node
+            && static_value.abs() < MATE_VALUE
+            && alpha.abs() < MATE_VALUE
+            && beta.abs() < MATE_VALUE;
+
+        // Razoring: Positions that are far below alpha close to the horizon are only
+        // checked for tactical resources
+        if self.selective_search.razoring
+            && is_static_value_reliable
+            && depth_left <= RAZORING_MAX_DEPTH
+            && static_value + RAZORING_MARGIN * (depth_left as i16) < alpha
+        {
+            let value = self.quiescence_search(state, depth, alpha, beta);
+            if value <= alpha {
+                return value;
+            }
+        }
+
+        // Null move pruning
+        let is_after_null_move = depth > 0 && self.action_stack[depth - 1] == Action::NONE;
+        if self.selective_search.null_move_pruning
+            && is_static_value_reliable
+            && !is_after_null_move
+            && depth_left > NULL_MOVE_REDUCTION
+            && static_value >= beta
+            && is_null_move_allowed(state, depth_left)
+        {
+            self.action_stack[depth] = Action::NONE;
+            self.do_null_action(state);
+            let value = -self.pv_search(
+                state,
+                depth + 1,
+                depth_left - 1 - NULL_MOVE_REDUCTION,
+                -beta,
+                -beta + 1,
+            );
+            self.undo_null_action(state);
+            if value >= beta && !self.stop {
+                // Don't trust mate values from null move searches
+                return if value >= MATE_VALUE { beta } else { value };
+            }
+        }
+
+        let previous_actions = [
+            if depth > 0 {
+                self.action_stack[depth - 1]
+            } else {
+                Action::NONE
+            },
+            if depth > 1 {
+                self.action_stack[depth - 2]
+            } else {
+                Action::NONE
+            },
+        ];
+        self.move_orderer.start(
             state,
             depth,
             pv_action,
             tt_action,
             &self.killer_heuristic[depth],
-            &self.history_heuristic[color],
-            &self.butterfly_heuristic[color],
+            previous_actions,
         );
-        if self.move_orderer.als[depth].size == 0 {
-            return MATE_VALUE;
-        }
 
-        let mut is_first = true;
+        let mut has_legal_actions = false;
+        let mut move_count = 0;
         loop {
-            let action = self.move_orderer.next(depth);
+            let (action, move_value) = self.move_orderer.next_with_value(
+                state,
+                depth,
+                &self.history_heuristic[color],
+                &self.butterfly_heuristic[color],
+            );
             if action == Action::NONE {
                 break;
             }
-            gamerules::do_action(state, action);
-            let value = if is_first {
-                is_first = false;
+            has_legal_actions = true;
+            if depth == 0 && self.excluded_root_actions.find_action(action).is_some() {
+                continue;
+            }
+            let is_quiet = !action.is_capture()
+                && !action.is_promotion(color)
+                && move_value < KILLER_MOVE_VALUE;
+            // Futility pruning: Quiet moves can't raise a hopeless static value above alpha
+            if self.selective_search.futility_pruning
+                && is_static_value_reliable
+                && move_count > 0
+                && is_quiet
+                && depth_left <= FUTILITY_MAX_DEPTH
+            {
+                let futility_value = static_value + FUTILITY_MARGIN * (depth_left as i16);
+                if futility_value <= alpha {
+                    best_value = best_value.max(futility_value);
+                    continue;
+                }
+            }
+            self.action_stack[depth] = action;
+            self.do_action(state, action);
+            let value = if move_count == 0 {
                 -self.pv_search(state, depth + 1, depth_left - 1, -beta, -alpha)
             } else {
-                let mut value =
-                    -self.pv_search(state, depth + 1, depth_left - 1, -alpha - 1, -alpha);
-                if value > alpha {
+                let reduction = if self.selective_search.late_move_reductions && is_quiet {
+                    late_move_reduction(depth_left, move_count, move_value)
+                } else {
+                    0
+                };
+                let mut value = -self.pv_search(
+                    state,
+                    depth + 1,
+                    depth_left - 1 - reduction,
+                    -alpha - 1,
+                    -alpha,
+                );
+                if value > alpha && reduction > 0 && !self.stop {
+                    value = -self.pv_search(state, depth + 1, depth_left - 1, -alpha - 1, -alpha);
+                }
+                if value > alpha && !self.stop {
                     value = -self.pv_search(state, depth + 1, depth_left - 1, -beta, -alpha);
                 }
                 value
             };
-            gamerules::undo_action(state, action);
+            self.undo_action(state, action);
+            if self.stop {
+                break;
+            }
+            move_count += 1;
             if value > best_value {
                 best_value = value;
                 self.pv_table[depth].clear();
@@ -261,8 +767,13 @@ impl Searcher {
                 if value > alpha {
                     alpha = value;
                     if alpha >= beta {
+                        let bonus = (depth_left as u64) * (depth_left as u64);
                         self.history_heuristic[color][action.from() as usize]
-                            [action.to() as usize] += (depth_left as u64) * (depth_left as u64);
+                            [action.to() as usize] += bonus;
+                        if !action.is_capture() && !action.is_promotion(color) {
+                            self.move_orderer
+                                .update_quiet_cutoff(color, depth, action, bonus);
+                        }
                         if action != self.killer_heuristic[depth][0]
                             && action != self.killer_heuristic[depth][1]
                         {
@@ -277,19 +788,75 @@ impl Searcher {
                 }
             }
         }
-        if !self.stop {
+        if !has_legal_actions {
+            return MATE_VALUE;
+        }
+        // The value of the root is incomplete if actions were excluded
+        if !self.stop && (depth > 0 || self.excluded_root_actions.size == 0) {
             self.tt.insert(
                 hash,
                 TranspositionTableEntry {
-                    value: best_value,
+                    value: value_to_tt(best_value, depth),
                     action: self.pv_table[depth][0],
                     depth: depth_left as u8,
-                    hash,
                     alpha: best_value <= original_alpha,
                     beta: alpha >= beta,
                 },
             );
         }
+        if best_value > STANDARD_VALUE {
+            best_value
+        } else {
+            alpha
+        }
+    }
+
+    fn quiescence_search(
+        &mut self,
+        state: &mut GameState,
+        depth: usize,
+        mut alpha: i16,
+        beta: i16,
+    ) -> i16 {
+        let color_sign = match state.get_current_color() {
+            0 => 1,
+            _ => -1,
+        };
+        self.check_stop();
+
+        if gamerules::is_game_over(state) {
+            let result = gamerules::game_result(state);
+            return (MATE_VALUE + MAX_SEARCH_DEPTH as i16 - depth as i16) * color_sign * result;
+        }
+
+        // Stand pat: The side to move is never forced to make a tactical move
+        let stand_pat = self.evaluate(state) * color_sign;
+        if self.stop || stand_pat >= beta {
+            return stand_pat;
+        }
+        if stand_pat > alpha {
+            alpha = stand_pat;
+        }
+
+        self.move_orderer.generate_tactical_moves(state, depth);
+        loop {
+            let action = self.move_orderer.next(depth);
+            if action == Action::NONE {
+                break;
+            }
+            self.do_action(state, action);
+            let value = -self.quiescence_search(state, depth + 1, -beta, -alpha);
+            self.undo_action(state, action);
+            if self.stop {
+                break;
+            }
+            if value > alpha {
+                alpha = value;
+                if alpha >= beta {
+                    break;
+                }
+            }
+        }
         alpha
     }
 }
@@ -0,0 +1,181 @@
+use super::evaluation::MATE_VALUE;
+use super::search::MAX_SEARCH_DEPTH;
+use game_sdk::action::Action;
+use game_sdk::gamestate::GameState;
+
+// Progress of the search after an iteration of the iterative deepening
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SearchInfo {
+    pub depth: usize,
+    // Value from the perspective of the player to move
+    pub value: i16,
+    // Number of plies until the end of the game if the value is a forced result,
+    // negative if the player to move loses
+    pub mate_distance: Option<i16>,
+    pub nodes: usize,
+    pub nps: f64,
+    // Microseconds since the start of the search
+    pub elapsed: u128,
+    pub pv: Vec<Action>,
+    // Fill status of the transposition table in per mille
+    pub hashfull: usize,
+    // The iteration was stopped before it was finished
+    pub canceled: bool,
+    // Rank of the root action in a multi PV analysis starting at 1, 0 in a normal search
+    pub multi_pv: usize,
+}
+
+impl SearchInfo {
+    pub fn mate_distance(value: i16) -> Option<i16> {
+        if value.abs() > MATE_VALUE {
+            let distance = MAX_SEARCH_DEPTH as i16 - (value.abs() - MATE_VALUE);
+            Some(distance * value.signum())
+        } else {
+            None
+        }
+    }
+
+    // The principal variation ends before the depth of the iteration
+    pub fn reached_end_of_search_tree(&self) -> bool {
+        !self.canceled && self.pv.len() < self.depth
+    }
+
+    // info depth <d> [multipv <k>] value <v> [mate <m>] nodes <n> nps <n> elapsed <μs> hashfull <h> [canceled] pv <action bits>
+    pub fn serialize(&self) -> String {
+        let mut s = format!("info depth {}", self.depth);
+        if self.multi_pv > 0 {
+            s.push_str(&format!(" multipv {}", self.multi_pv));
+        }
+        s.push_str(&format!(" value {}", self.value));
+        if let Some(distance) = self.mate_distance {
+            s.push_str(&format!(" mate {}", distance));
+        }
+        s.push_str(&format!(
+            " nodes {} nps {:.0} elapsed {} hashfull {}",
+            self.nodes, self.nps, self.elapsed, self.hashfull
+        ));
+        if self.canceled {
+            s.push_str(" canceled");
+        }
+        s.push_str(" pv");
+        for action in self.pv.iter() {
+            // All bits, so the capture and amber flags of the actions are kept
+            s.push_str(&format!(" {}", action.bits()));
+        }
+        s
+    }
+
+    pub fn deserialize(line: &str) -> Option<Self> {
+        let mut tokens = line.split_whitespace();
+        if tokens.next()? != "info" {
+            return None;
+        }
+        let mut info = Self::default();
+        while let Some(key) = tokens.next() {
+            match key {
+                "depth" => info.depth = tokens.next()?.parse().ok()?,
+                "multipv" => info.multi_pv = tokens.next()?.parse().ok()?,
+                "value" => info.value = tokens.next()?.parse().ok()?,
+                "mate" => info.mate_distance = Some(tokens.next()?.parse().ok()?),
+                "nodes" => info.nodes = tokens.next()?.parse().ok()?,
+                "nps" => info.nps = tokens.next()?.parse().ok()?,
+                "elapsed" => info.elapsed = tokens.next()?.parse().ok()?,
+                "hashfull" => info.hashfull = tokens.next()?.parse().ok()?,
+                "canceled" => info.canceled = true,
+                "pv" => {
+                    for action in tokens.by_ref() {
+                        info.pv.push(Action::from_bits(action.parse().ok()?));
+                    }
+                }
+                _ => return None,
+            }
+        }
+        Some(info)
+    }
+}
+
+pub trait SearchObserver: Send {
+    fn on_search_start(&mut self, _state: &GameState) {}
+
+    fn on_iteration(&mut self, info: &SearchInfo);
+
+    // last_info is the last finished iteration or None if no iteration was finished
+    fn on_search_end(&mut self, _best_action: Action, _last_info: Option<&SearchInfo>) {}
+}
+
+impl<F: FnMut(&SearchInfo) + Send> SearchObserver for F {
+    fn on_iteration(&mut self, info: &SearchInfo) {
+        self(info)
+    }
+}
+
+// Prints the progress of the search as a human readable table
+#[derive(Default)]
+pub struct TablePrinter;
+
+impl TablePrinter {
+    fn format_pv(pv: &[Action]) -> String {
+        let mut s = String::new();
+        let mut line_length = 0;
+        for action in pv.iter() {
+            let next_action = &format!("{} ", action);
+            let len = next_action.len();
+            if line_length + len > 100 {
+                s.push_str("\n    .      .         .           .         .    . ");
+                line_length = 0;
+            }
+            line_length += len;
+            s.push_str(next_action);
+        }
+        s
+    }
+
+    fn print_end_of_search_tree(info: &SearchInfo) {
+        println!("Reached the end of the search tree.");
+        match info.mate_distance {
+            Some(distance) if distance > 0 => {
+                println!("Mate in {}. Value: +{}", distance, info.value - MATE_VALUE)
+            }
+            Some(distance) => println!("Mated in {} Value: {}", -distance, info.value + MATE_VALUE),
+            None => println!("Draw in {}", info.pv.len()),
+        }
+    }
+}
+
+impl SearchObserver for TablePrinter {
+    fn on_search_start(&mut self, state: &GameState) {
+        println!("Searching action using PV-Search for {}", state.to_fen());
+        println!("Depth  Value     Nodes     Elapsed   Nodes/s Hash PV");
+    }
+
+    fn on_iteration(&mut self, info: &SearchInfo) {
+        print!(
+            "{:5} {:6} {:9} {:9}μs {:9.0} {:4} ",
+            info.depth, info.value, info.nodes, info.elapsed, info.nps, info.hashfull
+        );
+        if info.canceled {
+            println!("(canceled)");
+            return;
+        }
+        println!("{}", Self::format_pv(&info.pv));
+        if info.reached_end_of_search_tree() {
+            Self::print_end_of_search_tree(info);
+        }
+    }
+
+    fn on_search_end(&mut self, _best_action: Action, last_info: Option<&SearchInfo>) {
+        if last_info.is_none() {
+            println!("No move found.");
+        }
+    }
+}
+
+// Prints every iteration as a machine readable line that can be read with SearchInfo::deserialize
+#[derive(Default)]
+pub struct InfoLinePrinter;
+
+impl SearchObserver for InfoLinePrinter {
+    fn on_iteration(&mut self, info: &SearchInfo) {
+        println!("{}", info.serialize());
+    }
+}
@@ -0,0 +1,82 @@
+use super::attacks::{amber_threats, Attacks};
+use game_sdk::gamestate::{GameState, BLUE, RED};
+
+pub const NULL_MOVE_REDUCTION: usize = 2;
+pub const LMR_MIN_DEPTH: usize = 3;
+pub const LMR_MIN_MOVES: usize = 3;
+pub const FUTILITY_MAX_DEPTH: usize = 2;
+pub const FUTILITY_MARGIN: i16 = 40;
+pub const RAZORING_MAX_DEPTH: usize = 2;
+pub const RAZORING_MARGIN: i16 = 120;
+// Maximum number of threat extensions on a single line
+pub const MAX_THREAT_EXTENSIONS: usize = 4;
+
+#[derive(Clone, Copy, Debug)]
+pub struct SelectiveSearch {
+    pub null_move_pruning: bool,
+    pub late_move_reductions: bool,
+    pub futility_pruning: bool,
+    pub razoring: bool,
+    pub threat_extensions: bool,
+}
+
+impl SelectiveSearch {
+    pub fn disabled() -> Self {
+        Self {
+            null_move_pruning: false,
+            late_move_reductions: false,
+            futility_pruning: false,
+            razoring: false,
+            threat_extensions: false,
+        }
+    }
+
+    // Whether one of the pruning techniques that need the static value is enabled
+    pub fn is_enabled(&self) -> bool {
+        self.null_move_pruning
+            || self.late_move_reductions
+            || self.futility_pruning
+            || self.razoring
+    }
+}
+
+// Only the techniques that won self-play matches against the search without them are
+// enabled by default. Null move pruning and razoring didn't gain anything yet.
+impl Default for SelectiveSearch {
+    fn default() -> Self {
+        Self {
+            null_move_pruning: false,
+            late_move_reductions: true,
+            futility_pruning: true,
+            razoring: false,
+            threat_extensions: true,
+        }
+    }
+}
+
+// Passing is only a lower bound for the best move if the side to move is not in zugzwang.
+// Once a player has an amber, the next amber decides the game and every tempo counts.
+// The same is true when the search can reach the ply limit and the tie-break.
+pub fn is_null_move_allowed(state: &GameState, depth_left: usize) -> bool {
+    state.ambers[RED] == 0 && state.ambers[BLUE] == 0 && state.ply as usize + depth_left < 59
+}
+
+#[inline(always)]
+pub fn late_move_reduction(depth_left: usize, move_count: usize, move_value: u64) -> usize {
+    if depth_left < LMR_MIN_DEPTH || move_count < LMR_MIN_MOVES {
+        return 0;
+    }
+    // Moves without any history are reduced further
+    let reduction = if move_count >= 8 || move_value == 0 {
+        2
+    } else {
+        1
+    };
+    reduction.min(depth_left - 2)
+}
+
+// Whether the opponent of the side to move could win an amber with the next action
+#[inline(always)]
+pub fn has_amber_threat(state: &GameState, attacks: &Attacks) -> bool {
+    amber_threats(state, state.get_current_color() ^ 1, attacks) > 0
+}
@@ -0,0 +1,71 @@
+use game_sdk::action::Action;
+use game_sdk::gamestate::GameState;
+
+// Share of the move time in percent after which no new iteration is started
+const OPENING_SOFT_LIMIT: u128 = 40;
+const MIDDLEGAME_SOFT_LIMIT: u128 = 60;
+// The game ends after 60 plies. Close to the end the remaining tree is small and
+// the search reaches the end of the game within a few iterations anyway.
+const ENDGAME_SOFT_LIMIT: u128 = 40;
+const OPENING_PLIES: u8 = 6;
+const ENDGAME_PLIES: u8 = 48;
+// Scales the soft limit in percent by the number of iterations in which the best action
+// did not change
+const STABILITY_SCALE: [u128; 5] = [150, 120, 100, 80, 60];
+// The soft limit is extended if the value dropped by at least this much since the last
+// iteration
+const VALUE_DROP: i16 = 30;
+const VALUE_DROP_SCALE: u128 = 150;
+
+// Decides after every finished iteration whether the next iteration is worth starting.
+// The search is always stopped at the hard limit.
+pub struct TimeManager {
+    pub soft_limit: u128,
+    pub hard_limit: u128,
+    best_action: Action,
+    stable_iterations: usize,
+    last_value: Option<i16>,
+    is_single_reply: bool,
+}
+
+impl TimeManager {
+    // movetime is the time in milliseconds that is available for this move
+    pub fn new(state: &GameState, movetime: u128, legal_actions: usize) -> Self {
+        let soft_limit_percent = if state.ply < OPENING_PLIES {
+            OPENING_SOFT_LIMIT
+        } else if state.ply < ENDGAME_PLIES {
+            MIDDLEGAME_SOFT_LIMIT
+        } else {
+            ENDGAME_SOFT_LIMIT
+        };
+        Self {
+            soft_limit: movetime * soft_limit_percent / 100,
+            hard_limit: movetime,
+            best_action: Action::NONE,
+            stable_iterations: 0,
+            last_value: None,
+            is_single_reply: legal_actions <= 1,
+        }
+    }
+
+    // elapsed is the time in milliseconds since the start of the search
+    pub fn should_stop(&mut self, elapsed: u128, best_action: Action, value: i16) -> bool {
+        if self.is_single_reply {
+            return true;
+        }
+        if best_action == self.best_action {
+            self.stable_iterations += 1;
+        } else {
+            self.best_action = best_action;
+            self.stable_iterations = 0;
+        }
+        let mut soft_limit = self.soft_limit
+            * STABILITY_SCALE[self.stable_iterations.min(STABILITY_SCALE.len() - 1)]
+            / 100;
+        if matches!(self.last_value, Some(last_value) if value <= last_value - VALUE_DROP) {
+            soft_limit = soft_limit * VALUE_DROP_SCALE / 100;
+        }
+        self.last_value = Some(value);
+        elapsed >= soft_limit.min(self.hard_limit)
+    }
+}
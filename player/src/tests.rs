@@ -0,0 +1,1015 @@
+use super::greedy::GreedyPlayer;
+use super::mcts::{MctsPlayer, RolloutPolicy};
+use super::minimax::attacks::{amber_threats, piece_attacks, AttackStack, Attacks};
+use super::minimax::cache::{TranspositionTable, TranspositionTableEntry};
+use super::minimax::evaluation::{
+    evaluate_with_attacks, evaluate_with_trace, static_evaluation, EvaluationParameters,
+    DEFAULT_PARAMETERS, ENDGAME, MATE_VALUE, OPENING,
+};
+use super::minimax::evaluator::{Evaluator, NeuralEvaluator};
+use super::minimax::limits::SearchLimits;
+use super::minimax::move_ordering::MoveOrderer;
+use super::minimax::nnue::{AccumulatorStack, NeuralNetwork, FILE_SIZE};
+use super::minimax::proof_number_search::{is_solvable, ProofNumberSearch, ProofResult};
+use super::minimax::search::{widen_aspiration_window, Searcher, MAX_VALUE, MIN_VALUE};
+use super::minimax::search_info::SearchInfo;
+use super::minimax::selectivity::{has_amber_threat, SelectiveSearch, MAX_THREAT_EXTENSIONS};
+use super::minimax::time_manager::TimeManager;
+use game_sdk::action::{Action, ActionList};
+use game_sdk::bitboard::FINISH_LINES;
+use game_sdk::gamerules;
+use game_sdk::gamestate::{GameState, BLUE, RED};
+use game_sdk::piece::{COCKLE, GULL, PIECES, SEAL};
+use game_sdk::player::Player;
+use rand::{rngs::SmallRng, RngCore, SeedableRng};
+use std::sync::atomic::AtomicBool;
+use std::sync::{Arc, Mutex};
+
+fn random_gamestate(rng: &mut SmallRng, ply: u8) -> GameState {
+    let mut state = GameState::random();
+    let mut al = ActionList::default();
+    while state.ply < ply && !gamerules::is_game_over(&state) {
+        gamerules::get_legal_actions(&state, &mut al);
+        let action = al[rng.next_u64() as usize % al.size];
+        gamerules::do_action(&mut state, action);
+    }
+    state
+}
+
+fn fixed_depth_value(searcher: &mut Searcher, state: &GameState, depth: usize) -> i16 {
+    let mut state = state.clone();
+    searcher.limits = SearchLimits::infinite();
+    searcher.prepare_search(&state);
+    let mut value = 0;
+    for depth in 1..=depth {
+        value = searcher.pv_search(&mut state, 0, depth, MIN_VALUE, MAX_VALUE);
+    }
+    value
+}
+
+#[test]
+pub fn test_tt_cutoffs() {
+    let mut rng = SmallRng::seed_from_u64(0);
+    let mut with_cutoffs = Searcher::default();
+    with_cutoffs.selective_search = SelectiveSearch::disabled();
+    let mut without_cutoffs = Searcher::default();
+    without_cutoffs.selective_search = SelectiveSearch::disabled();
+    without_cutoffs.use_tt_cutoffs = false;
+    for i in 0..20 {
+        let state = random_gamestate(&mut rng, (i % 10) as u8);
+        let expected = fixed_depth_value(&mut without_cutoffs, &state, 4);
+        let value = fixed_depth_value(&mut with_cutoffs, &state, 4);
+        assert_eq!(value, expected, "{}", state.to_fen());
+    }
+}
+
+#[test]
+pub fn test_threaded_search() {
+    let mut rng = SmallRng::seed_from_u64(1);
+    let mut al = ActionList::default();
+    let mut searcher = Searcher::default();
+    searcher.set_threads(4);
+    searcher.set_time_limit(100);
+    for ply in [0, 20, 40] {
+        let state = random_gamestate(&mut rng, ply);
+        let action = searcher.on_move_request(&state);
+        gamerules::get_legal_actions(&state, &mut al);
+        assert!(al.find_action(action).is_some());
+    }
+    assert!(searcher.tt.get_fill_status() > 0);
+}
+
+#[test]
+pub fn test_selective_search() {
+    let mut rng = SmallRng::seed_from_u64(2);
+    let mut al = ActionList::default();
+    let options = [
+        SelectiveSearch::default(),
+        SelectiveSearch {
+            null_move_pruning: true,
+            ..SelectiveSearch::disabled()
+        },
+        SelectiveSearch {
+            late_move_reductions: true,
+            ..SelectiveSearch::disabled()
+        },
+        SelectiveSearch {
+            futility_pruning: true,
+            ..SelectiveSearch::disabled()
+        },
+        SelectiveSearch {
+            razoring: true,
+            ..SelectiveSearch::disabled()
+        },
+    ];
+    let mut searcher = Searcher::default();
+    for selective_search in options {
+        searcher.selective_search = selective_search;
+        for ply in [0, 15, 30] {
+            let state = random_gamestate(&mut rng, ply);
+            if gamerules::is_game_over(&state) {
+                continue;
+            }
+            let mut toy_state = state.clone();
+            fixed_depth_value(&mut searcher, &state, 5);
+            let pv = searcher.pv_table[0].clone();
+            assert!(pv.size > 0);
+            for i in 0..pv.size {
+                gamerules::get_legal_actions(&toy_state, &mut al);
+                assert!(al.find_action(pv[i]).is_some());
+                gamerules::do_action(&mut toy_state, pv[i]);
+            }
+            assert!(toy_state.check_integrity());
+        }
+    }
+}
+
+#[test]
+pub fn test_evaluation_parameters_serialization() {
+    let mut parameters = EvaluationParameters::default();
+    parameters.reachable_fields_value[2] = 2.5;
+    let json = parameters.to_json();
+    assert_eq!(EvaluationParameters::from_json(&json).unwrap(), parameters);
+    assert_ne!(parameters, DEFAULT_PARAMETERS);
+    assert!(EvaluationParameters::from_json("{}").is_err());
+}
+
+fn random_network(rng: &mut SmallRng) -> NeuralNetwork {
+    let mut bytes = vec![0u8; FILE_SIZE];
+    for byte in bytes.iter_mut() {
+        *byte = (rng.next_u32() % 7) as u8;
+    }
+    bytes[0..4].copy_from_slice(b"SCNN");
+    NeuralNetwork::from_bytes(&bytes).unwrap()
+}
+
+#[test]
+pub fn test_nnue_incremental_updates() {
+    let mut rng = SmallRng::seed_from_u64(3);
+    let mut al = ActionList::default();
+    let mut history = ActionList::default();
+    let network = random_network(&mut rng);
+    assert!(NeuralNetwork::from_bytes(&[0; 16]).is_err());
+    let mut accumulators = AccumulatorStack::default();
+    for _ in 0..50 {
+        let mut state = GameState::random();
+        accumulators.reset(&network, &state);
+        history.clear();
+        while !gamerules::is_game_over(&state) {
+            gamerules::get_legal_actions(&state, &mut al);
+            let action = al[rng.next_u64() as usize % al.size];
+            gamerules::do_action(&mut state, action);
+            accumulators.push(&network, &state);
+            history.push(action);
+            assert_eq!(accumulators.top().values, network.refresh(&state).values);
+            gamerules::do_null_action(&mut state);
+            accumulators.push(&network, &state);
+            assert_eq!(accumulators.top().values, network.refresh(&state).values);
+            gamerules::undo_null_action(&mut state);
+            accumulators.pop();
+        }
+        for i in (0..history.size).rev() {
+            gamerules::undo_action(&mut state, history[i]);
+            accumulators.pop();
+            let expected = network.refresh(&state);
+            assert_eq!(accumulators.top().values, expected.values);
+            assert_eq!(
+                network.evaluate(accumulators.top()),
+                network.evaluate(&expected)
+            );
+        }
+    }
+}
+
+#[test]
+pub fn test_nnue_search() {
+    let mut rng = SmallRng::seed_from_u64(4);
+    let mut al = ActionList::default();
+    let mut searcher =
+        Searcher::with_evaluator(NeuralEvaluator::new(Arc::new(random_network(&mut rng))));
+    searcher.set_time_limit(50);
+    let state = random_gamestate(&mut rng, 10);
+    let action = searcher.on_move_request(&state);
+    gamerules::get_legal_actions(&state, &mut al);
+    assert!(al.find_action(action).is_some());
+}
+
+#[test]
+pub fn test_mcts() {
+    let mut rng = SmallRng::seed_from_u64(9);
+    let mut al = ActionList::default();
+    for rollout_policy in [
+        RolloutPolicy::Random,
+        RolloutPolicy::Greedy,
+        RolloutPolicy::Evaluation,
+    ] {
+        let mut player = MctsPlayer::default();
+        let infos = Arc::new(Mutex::new(Vec::new()));
+        let observer_infos = Arc::clone(&infos);
+        player.observers = vec![Box::new(move |info: &SearchInfo| {
+            observer_infos.lock().unwrap().push(info.clone())
+        })];
+        player.rollout_policy = rollout_policy;
+        player.set_time_limit(100);
+        let mut state = random_gamestate(&mut rng, 10);
+        let action = player.on_move_request(&state);
+        gamerules::get_legal_actions(&state, &mut al);
+        assert!(al.find_action(action).is_some());
+        // The observers get the result of the search
+        {
+            let infos = infos.lock().unwrap();
+            assert_eq!(infos.len(), 1);
+            assert_eq!(infos[0].pv[0], action);
+            assert_eq!(infos[0].depth, infos[0].pv.len());
+            assert_eq!(infos[0].nodes, player.iterations);
+        }
+        // The subtree of the chosen action is kept for the next search
+        let visits = player.root_visits();
+        gamerules::do_action(&mut state, action);
+        player.set_time_limit(0);
+        player.on_move_request(&state);
+        assert!(player.root_visits() > 0);
+        assert!(player.root_visits() < visits);
+        player.reset();
+        player.on_move_request(&state);
+        assert_eq!(player.root_visits(), 0);
+    }
+
+    // Blue has no legal actions after red captures its last piece
+    let mut state = GameState::empty();
+    state.board[RED][GULL as usize] = 1 << 27;
+    state.occupied[RED] = 1 << 27;
+    state.board[BLUE][SEAL as usize] = 1 << 28;
+    state.occupied[BLUE] = 1 << 28;
+    for rollout_policy in [
+        RolloutPolicy::Random,
+        RolloutPolicy::Greedy,
+        RolloutPolicy::Evaluation,
+    ] {
+        let mut player = MctsPlayer::default();
+        player.rollout_policy = rollout_policy;
+        player.set_time_limit(20);
+        let action = player.on_move_request(&state);
+        gamerules::get_legal_actions(&state, &mut al);
+        assert!(al.find_action(action).is_some());
+        let mut next_state = state.clone();
+        gamerules::do_action(&mut next_state, Action::new(27, 28, GULL, true, false));
+        player.reset();
+        assert_eq!(player.on_move_request(&next_state), Action::NONE);
+    }
+}
+
+#[test]
+pub fn test_greedy_player() {
+    let mut rng = SmallRng::seed_from_u64(21);
+    let mut al = ActionList::default();
+    let mut greedy_player = GreedyPlayer::default();
+    let parameters = EvaluationParameters::default();
+    for i in 0..20 {
+        let mut state = random_gamestate(&mut rng, 10 + i % 2);
+        gamerules::get_legal_actions(&state, &mut al);
+        let color_sign = if state.get_current_color() == RED {
+            1
+        } else {
+            -1
+        };
+        let mut best_value = i16::MIN;
+        for i in 0..al.size {
+            gamerules::do_action(&mut state, al[i]);
+            best_value = best_value.max(static_evaluation(&state, &parameters) * color_sign);
+            gamerules::undo_action(&mut state, al[i]);
+        }
+        let action = greedy_player.get_action(&state);
+        gamerules::do_action(&mut state, action);
+        assert_eq!(
+            static_evaluation(&state, &parameters) * color_sign,
+            best_value
+        );
+    }
+}
+
+#[test]
+pub fn test_search_info() {
+    let mut rng = SmallRng::seed_from_u64(10);
+    let infos = Arc::new(Mutex::new(Vec::new()));
+    let mut searcher = Searcher::default();
+    let observer_infos = Arc::clone(&infos);
+    searcher.observers = vec![Box::new(move |info: &SearchInfo| {
+        observer_infos.lock().unwrap().push(info.clone())
+    })];
+    searcher.limits = SearchLimits::depth(5);
+    // Extended lines would be longer than the depth
+    searcher.selective_search.threat_extensions = false;
+    let state = random_gamestate(&mut rng, 8);
+    let action = searcher.search(&state);
+    let infos = infos.lock().unwrap();
+    assert_eq!(infos.len(), 5);
+    for (i, info) in infos.iter().enumerate() {
+        assert_eq!(info.depth, i + 1);
+        assert!(!info.canceled);
+        assert_eq!(info.pv.len(), info.depth);
+        assert_eq!(
+            SearchInfo::deserialize(&info.serialize()).unwrap().pv,
+            info.pv
+        );
+        assert_eq!(SearchInfo::deserialize(&info.serialize()).unwrap(), *info);
+    }
+    assert_eq!(infos[4].pv[0], action);
+    assert_eq!(infos[4].value, searcher.best_value);
+    assert!(infos.windows(2).all(|w| w[0].nodes < w[1].nodes));
+    assert_eq!(SearchInfo::mate_distance(MATE_VALUE + 57), Some(3));
+    assert_eq!(SearchInfo::mate_distance(-MATE_VALUE - 56), Some(-4));
+    assert_eq!(SearchInfo::mate_distance(100), None);
+}
+
+#[test]
+pub fn test_search_limits() {
+    let mut rng = SmallRng::seed_from_u64(11);
+    let state = random_gamestate(&mut rng, 12);
+    let mut al = ActionList::default();
+    gamerules::get_legal_actions(&state, &mut al);
+    let mut searcher = Searcher::default();
+    searcher.observers.clear();
+
+    searcher.limits = SearchLimits::nodes(5000);
+    let action = searcher.search(&state);
+    assert!(
+        searcher.nodes_searched <= 5000,
+        "{}",
+        searcher.nodes_searched
+    );
+    assert!(al.find_action(action).is_some());
+
+    // The stop handle aborts an infinite search from another thread
+    let depths = Arc::new(Mutex::new(Vec::new()));
+    let observer_depths = Arc::clone(&depths);
+    searcher.observers = vec![Box::new(move |info: &SearchInfo| {
+        if !info.canceled {
+            observer_depths
+                .lock()
+                .unwrap()
+                .push((info.depth, info.pv[0]))
+        }
+    })];
+    searcher.limits = SearchLimits::infinite();
+    searcher.set_threads(2);
+    let stop_handle = searcher.stop_handle();
+    let start_time = std::time::Instant::now();
+    let action = std::thread::scope(|scope| {
+        scope.spawn(|| {
+            std::thread::sleep(std::time::Duration::from_millis(200));
+            stop_handle.store(true, std::sync::atomic::Ordering::Relaxed);
+        });
+        searcher.search(&state)
+    });
+    assert!(start_time.elapsed().as_millis() < 5000);
+    let depths = depths.lock().unwrap();
+    assert!(!depths.is_empty());
+    assert_eq!(depths.last().unwrap().1, action);
+    assert!(al.find_action(action).is_some());
+}
+
+#[test]
+pub fn test_multi_pv() {
+    let mut rng = SmallRng::seed_from_u64(12);
+    let mut al = ActionList::default();
+    for _ in 0..5 {
+        let state = random_gamestate(&mut rng, 10);
+        gamerules::get_legal_actions(&state, &mut al);
+        let mut searcher = Searcher::default();
+        searcher.observers.clear();
+        searcher.selective_search = SelectiveSearch::disabled();
+        searcher.limits = SearchLimits::depth(4);
+        let lines = searcher.analyze(&state, 3);
+        assert_eq!(lines.len(), 3.min(al.size));
+        for (i, line) in lines.iter().enumerate() {
+            assert_eq!(line.multi_pv, i + 1);
+            assert!(al.find_action(line.pv[0]).is_some());
+            assert!(lines[..i].iter().all(|other| other.pv[0] != line.pv[0]));
+            assert!(i == 0 || lines[i - 1].value >= line.value);
+        }
+        let mut reference = Searcher::default();
+        reference.selective_search = SelectiveSearch::disabled();
+        assert_eq!(lines[0].value, fixed_depth_value(&mut reference, &state, 4));
+    }
+}
+
+#[test]
+pub fn test_transposition_table() {
+    let tt = TranspositionTable::with_size(1);
+    let buckets = tt.capacity() / 4;
+    let entry = |depth: u8| TranspositionTableEntry {
+        value: -(depth as i16),
+        action: Action::NONE,
+        depth,
+        alpha: false,
+        beta: true,
+    };
+    // Five positions that share the same bucket
+    let hashes: Vec<usize> = (0..5).map(|i| 12345 + i * buckets).collect();
+    for (i, &hash) in hashes[..4].iter().enumerate() {
+        tt.insert(hash, entry(10 + i as u8));
+    }
+    for (i, &hash) in hashes[..4].iter().enumerate() {
+        let found = tt.lookup(hash).unwrap();
+        assert_eq!(found.depth, 10 + i as u8);
+        assert_eq!(found.value, -10 - i as i16);
+        assert!(found.beta && !found.alpha);
+    }
+    // Shallower entries of the same position don't replace deeper ones
+    tt.insert(hashes[0], entry(3));
+    assert_eq!(tt.lookup(hashes[0]).unwrap().depth, 10);
+    // The shallowest entry of the bucket is replaced
+    tt.insert(hashes[4], entry(5));
+    assert!(tt.lookup(hashes[0]).is_none());
+    assert_eq!(tt.lookup(hashes[4]).unwrap().depth, 5);
+    // Entries of previous searches are replaced first, even if they are deeper
+    tt.new_search();
+    tt.insert(hashes[1], entry(2));
+    assert_eq!(tt.lookup(hashes[1]).unwrap().depth, 2);
+    tt.insert(hashes[0], entry(1));
+    assert!(tt.lookup(hashes[1]).is_some());
+    assert!(tt.lookup(hashes[0]).is_some());
+    assert_eq!(tt.get_fill_status(), 4);
+    tt.clear();
+    assert_eq!(tt.get_fill_status(), 0);
+    assert_eq!(tt.hashfull(), 0);
+}
+
+fn zero_parameters() -> EvaluationParameters {
+    let mut parameters = EvaluationParameters::default();
+    for weight in parameters.weights_mut() {
+        *weight = 0.0;
+    }
+    parameters
+}
+
+#[test]
+pub fn test_game_phase_evaluation() {
+    const FIRST_COLUMN: u64 = 0x0101010101010101;
+    const LAST_COLUMN: u64 = FIRST_COLUMN << 7;
+    let mut rng = SmallRng::seed_from_u64(15);
+    for _ in 0..100 {
+        let mut state = random_gamestate(&mut rng, 6);
+        state.ambers = [0, 0];
+        // Remove a blue cockle so that the material is unbalanced
+        let blue_cockles = state.board[BLUE][COCKLE as usize];
+        if blue_cockles == 0 {
+            continue;
+        }
+        let removed = blue_cockles & blue_cockles.wrapping_neg();
+        state.board[BLUE][COCKLE as usize] ^= removed;
+        state.occupied[BLUE] ^= removed;
+        state.stacked &= !removed;
+        let cockles = [
+            state.board[RED][COCKLE as usize].count_ones() as i16,
+            state.board[BLUE][COCKLE as usize].count_ones() as i16,
+        ];
+
+        let mut parameters = zero_parameters();
+        parameters.piece_values[OPENING][COCKLE as usize] = 10.0;
+        parameters.piece_values[ENDGAME][COCKLE as usize] = 30.0;
+        for (ply, scale) in [(0, 10), (30, 20), (60, 30)] {
+            state.ply = ply;
+            assert_eq!(
+                static_evaluation(&state, &parameters),
+                (cockles[RED] - cockles[BLUE]) * scale
+            );
+        }
+
+        // Every piece on the first column of its own side is worth one point
+        let mut parameters = zero_parameters();
+        for table in parameters.piece_square_tables[ENDGAME].iter_mut() {
+            for row in table.iter_mut() {
+                row[0] = 1.0;
+            }
+        }
+        let red = (state.occupied[RED] & FIRST_COLUMN).count_ones() as i16;
+        let blue = (state.occupied[BLUE] & LAST_COLUMN).count_ones() as i16;
+        state.ply = 60;
+        assert_eq!(static_evaluation(&state, &parameters), red - blue);
+        state.ply = 0;
+        assert_eq!(static_evaluation(&state, &parameters), 0);
+    }
+}
+
+#[test]
+pub fn test_tie_break_evaluation() {
+    let mut state = GameState::empty();
+    let place = |state: &mut GameState, color: usize, piece: u8, square: u32| {
+        state.board[color][piece as usize] |= 1 << square;
+        state.occupied[color] |= 1 << square;
+    };
+    // Red has a cockle one column in front of the finish line, blue only has one three
+    // columns away. The blue seal next to the finish line doesn't count.
+    place(&mut state, RED, COCKLE, 6);
+    place(&mut state, RED, SEAL, 40);
+    place(&mut state, BLUE, COCKLE, 27);
+    place(&mut state, BLUE, SEAL, 9);
+    state.ply = 60;
+    state.recalculate_hash();
+    assert_eq!(gamerules::tie_break(&state), 1);
+    assert_eq!(gamerules::game_result(&state), 1);
+
+    let mut parameters = zero_parameters();
+    parameters.tie_break_value = 60.0;
+    for (ply, value) in [(60, 60), (30, 15), (0, 0)] {
+        state.ply = ply;
+        assert_eq!(static_evaluation(&state, &parameters), value);
+    }
+
+    // A blue gull on the same column as the red cockle, the next columns decide
+    let mut blue_state = state.clone();
+    place(&mut blue_state, BLUE, GULL, 17);
+    blue_state.ply = 60;
+    assert_eq!(gamerules::tie_break(&blue_state), -1);
+    assert_eq!(gamerules::game_result(&blue_state), -1);
+    assert_eq!(static_evaluation(&blue_state, &parameters), -60);
+
+    // The tie-break doesn't matter if the ambers are not equal
+    blue_state.ambers[BLUE] = 1;
+    assert_eq!(static_evaluation(&blue_state, &parameters), 0);
+}
+
+#[test]
+pub fn test_amber_threats() {
+    // The set-wise calculation has to find the same pieces as checking every piece alone
+    let per_piece = |state: &GameState, color: usize| {
+        let mut threats = 0;
+        for piece in PIECES {
+            let mut pieces = state.board[color][piece as usize];
+            while pieces > 0 {
+                let bit = 1 << pieces.trailing_zeros();
+                pieces ^= bit;
+                let attacks = piece_attacks(color, piece, bit);
+                let mut targets = attacks & state.occupied[color ^ 1] & state.stacked;
+                if bit & state.stacked > 0 {
+                    targets |= attacks & state.occupied[color ^ 1];
+                }
+                if piece != SEAL {
+                    targets |= attacks & FINISH_LINES[color] & !state.occupied[color];
+                }
+                if targets > 0 {
+                    threats |= bit;
+                }
+            }
+        }
+        threats
+    };
+    let mut rng = SmallRng::seed_from_u64(23);
+    for i in 0..200 {
+        let state = random_gamestate(&mut rng, (i % 50) as u8);
+        let attacks = Attacks::from_state(&state);
+        for color in [RED, BLUE] {
+            assert_eq!(
+                amber_threats(&state, color, &attacks),
+                per_piece(&state, color),
+                "{}",
+                state.to_fen()
+            );
+        }
+    }
+}
+
+#[test]
+pub fn test_attack_map_evaluation() {
+    let place = |state: &mut GameState, color: usize, piece: u8, square: u32| {
+        state.board[color][piece as usize] |= 1 << square;
+        state.occupied[color] |= 1 << square;
+    };
+    let mut parameters = zero_parameters();
+    parameters.hanging_piece_value = -10.0;
+    parameters.defended_stack_value = 7.0;
+    parameters.multiple_amber_threats_value = 50.0;
+
+    // The red cockle on (3, 3) is attacked by the blue seal on (5, 4)
+    let mut state = GameState::empty();
+    place(&mut state, RED, COCKLE, 27);
+    place(&mut state, BLUE, SEAL, 37);
+    assert_eq!(static_evaluation(&state, &parameters), -10);
+    // The red gull on (2, 3) defends it
+    place(&mut state, RED, GULL, 26);
+    assert_eq!(static_evaluation(&state, &parameters), 0);
+    state.stacked |= 1 << 27;
+    assert_eq!(static_evaluation(&state, &parameters), 7);
+
+    // The red cockle on (6, 2) can reach the finish line on two fields, but it is still
+    // a single threat
+    let mut state = GameState::empty();
+    place(&mut state, RED, COCKLE, 22);
+    place(&mut state, BLUE, SEAL, 63);
+    assert_eq!(static_evaluation(&state, &parameters), 0);
+    // The red gull on (6, 0) is a second threat
+    place(&mut state, RED, GULL, 6);
+    assert_eq!(static_evaluation(&state, &parameters), 50);
+
+    // The stacked red gull on (3, 3) can capture the hanging blue cockle on (4, 3)
+    let mut state = GameState::empty();
+    place(&mut state, RED, GULL, 27);
+    state.stacked |= 1 << 27;
+    place(&mut state, BLUE, COCKLE, 28);
+    assert_eq!(static_evaluation(&state, &parameters), 10);
+    // Together with the red gull on (6, 0) that reaches the finish line
+    place(&mut state, RED, GULL, 6);
+    assert_eq!(static_evaluation(&state, &parameters), 60);
+}
+
+#[test]
+pub fn test_evaluation_trace() {
+    let mut rng = SmallRng::seed_from_u64(18);
+    let parameters = EvaluationParameters::default();
+    for ply in 0..60 {
+        let state = random_gamestate(&mut rng, ply);
+        let trace = evaluate_with_trace(&state, &parameters);
+        assert_eq!(trace.value, static_evaluation(&state, &parameters));
+    }
+
+    // Red has an amber and its cockle on (6, 2) can reach the finish line
+    let mut state = GameState::empty();
+    state.board[RED][COCKLE as usize] |= 1 << 22;
+    state.occupied[RED] |= 1 << 22;
+    state.ambers[RED] = 1;
+    let trace = evaluate_with_trace(&state, &parameters);
+    assert_eq!(trace.mate_shortcut, Some(MATE_VALUE));
+    assert_eq!(trace.value, MATE_VALUE);
+    assert_eq!(trace.features[RED][0], (1.0, parameters.amber_value));
+}
+
+#[test]
+pub fn test_incremental_attacks() {
+    let mut rng = SmallRng::seed_from_u64(19);
+    let mut al = ActionList::default();
+    let mut attacks = AttackStack::default();
+    let parameters = EvaluationParameters::default();
+    for _ in 0..100 {
+        let mut state = GameState::random();
+        let mut history = ActionList::default();
+        attacks.reset(&state);
+        while !gamerules::is_game_over(&state) {
+            gamerules::get_legal_actions(&state, &mut al);
+            let action = al[rng.next_u64() as usize % al.size];
+            gamerules::do_action(&mut state, action);
+            attacks.push(&state, action);
+            history.push(action);
+            assert_eq!(*attacks.top(), Attacks::from_state(&state));
+            assert_eq!(
+                evaluate_with_attacks(&state, &parameters, attacks.top()),
+                static_evaluation(&state, &parameters)
+            );
+            gamerules::do_null_action(&mut state);
+            attacks.push_null();
+            assert_eq!(*attacks.top(), Attacks::from_state(&state));
+            gamerules::undo_null_action(&mut state);
+            attacks.pop();
+        }
+        for i in (0..history.size).rev() {
+            gamerules::undo_action(&mut state, history[i]);
+            attacks.pop();
+            assert_eq!(*attacks.top(), Attacks::from_state(&state));
+        }
+    }
+}
+
+// Checks that the players keep the evaluator in sync with the state
+#[derive(Clone, Default)]
+struct MockEvaluator {
+    hashes: Vec<u64>,
+    evaluations: Arc<Mutex<usize>>,
+}
+
+impl Evaluator for MockEvaluator {
+    fn reset(&mut self, state: &GameState) {
+        self.hashes = vec![state.hash];
+    }
+
+    fn push(&mut self, state: &GameState, _action: Action) {
+        self.hashes.push(state.hash);
+    }
+
+    fn push_null(&mut self, state: &GameState) {
+        self.hashes.push(state.hash);
+    }
+
+    fn pop(&mut self) {
+        self.hashes.pop();
+    }
+
+    fn evaluate(&self, state: &GameState) -> i16 {
+        assert_eq!(*self.hashes.last().unwrap(), state.hash);
+        *self.evaluations.lock().unwrap() += 1;
+        state.occupied[RED].count_ones() as i16 - state.occupied[BLUE].count_ones() as i16
+    }
+}
+
+#[test]
+pub fn test_evaluator() {
+    let mut rng = SmallRng::seed_from_u64(20);
+    let mut al = ActionList::default();
+    for _ in 0..5 {
+        let state = random_gamestate(&mut rng, 10);
+        gamerules::get_legal_actions(&state, &mut al);
+
+        let evaluator = MockEvaluator::default();
+        let evaluations = Arc::clone(&evaluator.evaluations);
+        let mut searcher = Searcher::with_evaluator(evaluator);
+        searcher.observers.clear();
+        searcher.limits = SearchLimits::depth(4);
+        let action = searcher.search(&state);
+        assert!(al.find_action(action).is_some());
+        assert!(*evaluations.lock().unwrap() > 0);
+
+        let mut greedy_player = GreedyPlayer::with_evaluator(MockEvaluator::default());
+        let action = greedy_player.on_move_request(&state);
+        assert!(al.find_action(action).is_some());
+    }
+}
+
+#[test]
+pub fn test_staged_move_generation() {
+    let mut rng = SmallRng::seed_from_u64(21);
+    let mut al = ActionList::default();
+    let mut move_orderer = MoveOrderer::default();
+    let history_heuristic = [[0; 64]; 64];
+    let butterfly_heuristic = [[1; 64]; 64];
+    let mut previous_actions = [Action::NONE; 2];
+    for _ in 0..20 {
+        let mut state = GameState::random();
+        while !gamerules::is_game_over(&state) {
+            gamerules::get_legal_actions(&state, &mut al);
+            let random_action = |rng: &mut SmallRng| {
+                if rng.next_u64().is_multiple_of(2) {
+                    al[rng.next_u64() as usize % al.size]
+                } else {
+                    Action::from_bits(rng.next_u64() as u16)
+                }
+            };
+            let (pv_action, tt_action) = (random_action(&mut rng), random_action(&mut rng));
+            let killers = [random_action(&mut rng), random_action(&mut rng)];
+            move_orderer.start(&state, 1, pv_action, tt_action, &killers, previous_actions);
+            let mut generated = ActionList::default();
+            loop {
+                let (action, _) = move_orderer.next_with_value(
+                    &state,
+                    1,
+                    &history_heuristic,
+                    &butterfly_heuristic,
+                );
+                if action == Action::NONE {
+                    break;
+                }
+                assert!(generated.find_action(action).is_none());
+                generated.push(action);
+            }
+            assert_eq!(generated.size, al.size);
+            if al.find_action(pv_action).is_some() {
+                assert_eq!(generated[0], pv_action);
+            }
+            for i in 0..al.size {
+                assert!(generated.find_action(al[i]).is_some());
+            }
+            let action = al[rng.next_u64() as usize % al.size];
+            move_orderer.update_quiet_cutoff(state.get_current_color(), 1, action, 1);
+            gamerules::do_action(&mut state, action);
+            previous_actions = [action, previous_actions[0]];
+        }
+    }
+}
+
+#[test]
+pub fn test_time_manager() {
+    let mut rng = SmallRng::seed_from_u64(22);
+    let state = random_gamestate(&mut rng, 20);
+    let mut al = ActionList::default();
+    gamerules::get_legal_actions(&state, &mut al);
+
+    // A single reply doesn't need a second iteration
+    let mut time_manager = TimeManager::new(&state, 1000, 1);
+    assert!(time_manager.should_stop(0, al[0], 0));
+
+    // The soft limit shrinks while the best action is stable
+    let mut time_manager = TimeManager::new(&state, 1000, al.size);
+    let soft_limit = time_manager.soft_limit;
+    assert!(soft_limit < time_manager.hard_limit);
+    assert!(!time_manager.should_stop(soft_limit, al[0], 0));
+    let mut iterations = 1;
+    while !time_manager.should_stop(soft_limit, al[0], 0) {
+        iterations += 1;
+    }
+    assert!(iterations < 5);
+
+    // A dropping value extends it
+    let elapsed = soft_limit * 8 / 5;
+    let mut time_manager = TimeManager::new(&state, 1000, al.size);
+    assert!(!time_manager.should_stop(0, al[0], 0));
+    assert!(time_manager.should_stop(elapsed, al[1], 0));
+    let mut time_manager = TimeManager::new(&state, 1000, al.size);
+    assert!(!time_manager.should_stop(0, al[0], 0));
+    assert!(!time_manager.should_stop(elapsed, al[1], -100));
+
+    // The hard limit is never exceeded
+    let mut time_manager = TimeManager::new(&state, 1000, al.size);
+    assert!(time_manager.should_stop(1000, al[1], -100));
+
+    // Searches with a single legal action return immediately
+    let mut state = GameState::empty();
+    state.board[RED][COCKLE as usize] = 1 << 6;
+    state.occupied[RED] = 1 << 6;
+    state.board[BLUE][SEAL as usize] = 1 << 63;
+    state.occupied[BLUE] = 1 << 63;
+    gamerules::get_legal_actions(&state, &mut al);
+    assert_eq!(al.size, 1);
+    let mut searcher = Searcher::default();
+    searcher.observers.clear();
+    searcher.set_time_limit(1000);
+    let start_time = std::time::Instant::now();
+    assert_eq!(searcher.search(&state), al[0]);
+    assert!(start_time.elapsed().as_millis() < 500);
+}
+
+#[test]
+pub fn test_aspiration_windows() {
+    // Without selectivity and transposition table cutoffs, the value does not depend on the
+    // window of the root or the order of the actions
+    let mut rng = SmallRng::seed_from_u64(17);
+    let mut searcher = Searcher::default();
+    searcher.observers.clear();
+    searcher.selective_search = SelectiveSearch::disabled();
+    searcher.use_tt_cutoffs = false;
+    searcher.limits = SearchLimits::depth(5);
+    let mut reference = Searcher::default();
+    reference.observers.clear();
+    reference.selective_search = SelectiveSearch::disabled();
+    reference.use_tt_cutoffs = false;
+    reference.limits = SearchLimits::depth(5);
+    reference.aspiration_windows = false;
+    for i in 0..40 {
+        let state = random_gamestate(&mut rng, (i % 25 * 2) as u8);
+        searcher.search(&state);
+        reference.search(&state);
+        assert_eq!(
+            searcher.best_value,
+            reference.best_value,
+            "{}",
+            state.to_fen()
+        );
+    }
+
+    // Small fails widen the window step by step, large ones open it completely
+    assert_eq!(widen_aspiration_window(10, 20, 70, 50), Some((-40, 70)));
+    assert_eq!(widen_aspiration_window(80, 20, 70, 50), Some((20, 130)));
+    assert_eq!(
+        widen_aspiration_window(10, 20, 70, 400),
+        Some((MIN_VALUE, 70))
+    );
+    assert_eq!(
+        widen_aspiration_window(80, 20, 70, 400),
+        Some((20, MAX_VALUE))
+    );
+    assert_eq!(widen_aspiration_window(30, 20, 70, 50), None);
+    // A wrong expected value is corrected by a re-search with a wider window
+    let mut searcher = Searcher::default();
+    searcher.selective_search = SelectiveSearch::disabled();
+    searcher.use_tt_cutoffs = false;
+    for i in 0..10 {
+        let mut state = random_gamestate(&mut rng, (i * 4) as u8);
+        let value = fixed_depth_value(&mut searcher, &state, 4);
+        if value.abs() >= MATE_VALUE {
+            continue;
+        }
+        for expected in [value - 150, value + 150] {
+            searcher.prepare_search(&state);
+            let aspiration_value = searcher.aspiration_search(&mut state, 4, expected);
+            assert_eq!(aspiration_value, value, "{}", state.to_fen());
+        }
+    }
+}
+
+#[test]
+pub fn test_threat_extensions() {
+    // A blue gull can reach the finish line
+    let mut state = GameState::empty();
+    state.board[BLUE][GULL as usize] = 1 << 25;
+    state.occupied[BLUE] = 1 << 25;
+    state.board[RED][COCKLE as usize] = 1 << 1;
+    state.occupied[RED] = 1 << 1;
+    assert!(has_amber_threat(&state, &Attacks::from_state(&state)));
+    // Blue can't threaten an amber on blue's turn
+    state.ply = 1;
+    assert!(!has_amber_threat(&state, &Attacks::from_state(&state)));
+    state.ply = 0;
+    // Seals can't move onto the finish line
+    state.board[BLUE][GULL as usize] = 0;
+    state.board[BLUE][SEAL as usize] = 1 << 25;
+    assert!(!has_amber_threat(&state, &Attacks::from_state(&state)));
+
+    // A blue gull can capture a red stack
+    let mut state = GameState::empty();
+    state.board[BLUE][GULL as usize] = 1 << 36;
+    state.occupied[BLUE] = 1 << 36;
+    state.board[RED][COCKLE as usize] = 1 << 37;
+    state.occupied[RED] = 1 << 37;
+    assert!(!has_amber_threat(&state, &Attacks::from_state(&state)));
+    state.stacked = 1 << 37;
+    assert!(has_amber_threat(&state, &Attacks::from_state(&state)));
+    // A blue stack can capture a red piece
+    state.stacked = 1 << 36;
+    assert!(has_amber_threat(&state, &Attacks::from_state(&state)));
+
+    // Extended lines are longer than the depth of the iteration, but never by more than the
+    // maximum number of extensions
+    let mut rng = SmallRng::seed_from_u64(18);
+    let infos = Arc::new(Mutex::new(Vec::new()));
+    let observer_infos = Arc::clone(&infos);
+    let mut searcher = Searcher::default();
+    searcher.observers = vec![Box::new(move |info: &SearchInfo| {
+        observer_infos.lock().unwrap().push(info.clone())
+    })];
+    searcher.selective_search.threat_extensions = true;
+    searcher.limits = SearchLimits::depth(4);
+    // Threats are extended whether the opponent already has an amber or not
+    for ambers in [[0, 0], [1, 1]] {
+        for i in 0..20 {
+            let mut state = random_gamestate(&mut rng, 10 + i);
+            state.ambers = ambers;
+            searcher.search(&state);
+        }
+        let mut infos = infos.lock().unwrap();
+        assert!(infos.iter().any(|info| info.pv.len() > info.depth));
+        for info in infos.iter() {
+            assert!(info.pv.len() <= info.depth + MAX_THREAT_EXTENSIONS);
+            if info.pv.len() > info.depth {
+                assert!(!info.reached_end_of_search_tree());
+            }
+        }
+        infos.clear();
+    }
+}
+
+#[test]
+pub fn test_proof_number_search() {
+    // Red has more ambers and blue can't win one, so every action of red wins
+    let mut state = GameState::empty();
+    state.ply = 54;
+    state.ambers[RED] = 1;
+    state.board[RED][GULL as usize] = 1 << 30;
+    state.occupied[RED] = 1 << 30;
+    state.board[BLUE][SEAL as usize] = 1 << 56;
+    state.occupied[BLUE] = 1 << 56;
+    assert!(is_solvable(&state));
+    let mut solver = ProofNumberSearch::default();
+    let proof = solver.solve(&state);
+    assert_eq!(proof.result, ProofResult::Win);
+    assert!(proof.best_action().is_some());
+    let mut searcher = Searcher::default();
+    searcher.observers.clear();
+    let action = searcher.search(&state);
+    assert_eq!(Some(action), proof.best_action());
+    assert!(searcher.best_value > MATE_VALUE);
+    // Few pieces are only solved close to the end of the game
+    state.ply = 30;
+    assert!(!is_solvable(&state));
+
+    // The solver gives up when it runs out of nodes or is stopped
+    let mut rng = SmallRng::seed_from_u64(20);
+    let state = random_gamestate(&mut rng, 20);
+    let mut limited_solver = ProofNumberSearch::default();
+    limited_solver.max_nodes = 100;
+    assert_eq!(limited_solver.solve(&state).result, ProofResult::Unknown);
+    let mut stopped_solver = ProofNumberSearch::default();
+    stopped_solver.stop_signal = Some(Arc::new(AtomicBool::new(true)));
+    assert_eq!(stopped_solver.solve(&state).result, ProofResult::Unknown);
+
+    // The results agree with a search to the end of the game
+    let mut rng = SmallRng::seed_from_u64(19);
+    let mut reference = Searcher::default();
+    reference.selective_search = SelectiveSearch::disabled();
+    for i in 0..20 {
+        let state = random_gamestate(&mut rng, 54 + i % 4);
+        if gamerules::is_game_over(&state) {
+            continue;
+        }
+        let value = fixed_depth_value(&mut reference, &state, 59 - state.ply as usize);
+        let proof = solver.solve(&state);
+        let expected = match value {
+            value if value > 0 => ProofResult::Win,
+            0 => ProofResult::Draw,
+            _ => ProofResult::Loss,
+        };
+        assert_eq!(proof.result, expected, "{}", state.to_fen());
+        // The opponent can't do better after the proven action
+        if let Some(action) = proof.best_action() {
+            let mut next_state = state.clone();
+            gamerules::do_action(&mut next_state, action);
+            let opponent_result = solver.solve(&next_state).result;
+            if proof.result == ProofResult::Win {
+                assert!(
+                    gamerules::is_game_over(&next_state) || opponent_result == ProofResult::Loss
+                );
+            } else {
+                assert!(
+                    gamerules::is_game_over(&next_state) || opponent_result != ProofResult::Win
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,24 @@
+#!/bin/sh
+# Searches every position of bench_positions.txt to a fixed depth and prints the sum of
+# the searched nodes, so that changes to the search can be compared by their node count.
+# The nodes per second are noisy and should be compared over several runs.
+# Usage: scripts/bench.sh [depth] [client options]
+# Example: scripts/bench.sh 10 --aspiration-windows false
+DEPTH=${1:-10}
+[ $# -gt 0 ] && shift
+cd "$(dirname "$0")/.." || exit 1
+cargo build --release --bin client || exit 1
+TOTAL=0
+ELAPSED=0
+while read -r FEN; do
+    # The time limit is disabled so that every search finishes the depth
+    INFO=$(echo "$FEN" | target/release/client --test true --time 10000000 \
+        --time-management false --endgame-solver false --info-lines true \
+        --depth "$DEPTH" "$@" 2>/dev/null | grep "^info depth" | tail -n 1)
+    NODES=$(echo "$INFO" | sed -E 's/.* nodes ([0-9]+) .*/\1/')
+    MICROS=$(echo "$INFO" | sed -E 's/.* elapsed ([0-9]+) .*/\1/')
+    TOTAL=$((TOTAL + NODES))
+    ELAPSED=$((ELAPSED + MICROS))
+done < scripts/bench_positions.txt
+echo "Nodes: $TOTAL"
+echo "NPS: $((TOTAL * 1000000 / ELAPSED))"
@@ -9,6 +9,12 @@ def main() -> None:
         for _ in range(2)
     ]
     print(f"pub const ZOBRIST_KEYS: [[[u64; 64]; 5]; 2] = {keys};")
+    amber_keys = [
+        [uuid.uuid4().int & (1<<64)-1 for _ in range(4)]
+        for _ in range(2)
+    ]
+    print(f"pub const AMBER_KEYS: [[u64; 4]; 2] = {amber_keys};")
+    print(f"pub const SIDE_TO_MOVE_KEY: u64 = {uuid.uuid4().int & (1<<64)-1};")
 
 if __name__ == "__main__":
     main()
\ No newline at end of file
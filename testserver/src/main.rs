@@ -2,9 +2,10 @@ use game_sdk::action::Action;
 use game_sdk::gamerules;
 use game_sdk::gamestate::{GameState, BLUE, RED};
 use game_sdk::player::Player;
+use player::minimax::search_info::SearchInfo;
 use std::fmt::{Display, Formatter, Result};
 use std::io::{BufRead, BufReader, Write};
-use std::process::{ChildStdin, ChildStdout, Command, Stdio};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
 use std::sync::{Arc, Mutex};
 use std::thread::{self, JoinHandle};
 
@@ -14,6 +15,8 @@ pub struct TestResult {
     one: usize,
     draw: usize,
     two: usize,
+    depth_sum: [usize; 2],
+    searches: [usize; 2],
 }
 
 impl Display for TestResult {
@@ -22,7 +25,16 @@ impl Display for TestResult {
             f,
             "Games: {:6} One: {:6} Draw: {:6} Two: {:6}",
             self.games_played, self.one, self.draw, self.two
-        )
+        )?;
+        if self.searches.iter().any(|&searches| searches > 0) {
+            write!(
+                f,
+                " Depth One: {:5.2} Two: {:5.2}",
+                self.average_depth(0),
+                self.average_depth(1)
+            )?;
+        }
+        Ok(())
     }
 }
 
@@ -35,17 +47,35 @@ impl TestResult {
             _ => self.two += 1,
         };
     }
+
+    pub fn add_search_info(&mut self, client: usize, info: &SearchInfo) {
+        self.depth_sum[client] += info.depth;
+        self.searches[client] += 1;
+    }
+
+    pub fn average_depth(&self, client: usize) -> f64 {
+        self.depth_sum[client] as f64 / self.searches[client].max(1) as f64
+    }
 }
 
 pub struct ClientInstance {
+    process: Child,
     stdin: ChildStdin,
     stdout: ChildStdout,
+    info_lines: bool,
+    // The last finished iteration of the last search, if the client prints info lines
+    pub last_info: Option<SearchInfo>,
 }
 
 impl ClientInstance {
-    pub fn new(path: String) -> Self {
-        let mut process = Command::new(path.clone())
-            .args(&["--test", "true"])
+    pub fn new(path: String, args: &[String], info_lines: bool) -> Self {
+        let mut command = Command::new(path.clone());
+        command.args(["--test", "true"]);
+        if info_lines {
+            command.args(["--info-lines", "true"]);
+        }
+        let mut process = command
+            .args(args)
             //.args(&["--time", "200"])
             .stdin(Stdio::piped())
             .stdout(Stdio::piped())
@@ -55,6 +85,9 @@ impl ClientInstance {
         Self {
             stdin: process.stdin.take().unwrap(),
             stdout: process.stdout.take().unwrap(),
+            process,
+            info_lines,
+            last_info: None,
         }
     }
 }
@@ -66,15 +99,19 @@ impl Player for ClientInstance {
         self.stdin.write_all(fen.as_bytes()).unwrap();
         let mut read = BufReader::new(&mut self.stdout);
         let mut line = String::new();
+        self.last_info = None;
         loop {
             read.read_line(&mut line).unwrap();
             if !line.is_empty() && line.contains("action: ") {
-                line = (&line[8..]).to_string();
+                line = line[8..].to_string();
                 break;
             }
-            if !line.is_empty() {
-                line.pop();
-                //println!("info: {}", line);
+            if self.info_lines {
+                if let Some(info) = SearchInfo::deserialize(&line) {
+                    if !info.canceled {
+                        self.last_info = Some(info);
+                    }
+                }
             }
             line.truncate(0);
         }
@@ -98,22 +135,33 @@ impl Player for ClientInstance {
     }
 }
 
+impl Drop for ClientInstance {
+    fn drop(&mut self) {
+        let _ = self.process.kill();
+        let _ = self.process.wait();
+    }
+}
+
 pub fn run_test(
-    client_one: String,
-    client_two: String,
+    client_one: (String, Vec<String>, bool),
+    client_two: (String, Vec<String>, bool),
     test_result: Arc<Mutex<TestResult>>,
 ) -> JoinHandle<()> {
-    let mut instance_one = ClientInstance::new(client_one);
-    let mut instance_two = ClientInstance::new(client_two);
+    let mut instance_one = ClientInstance::new(client_one.0, &client_one.1, client_one.2);
+    let mut instance_two = ClientInstance::new(client_two.0, &client_two.1, client_two.2);
     let mut first_player = RED;
     thread::spawn(move || loop {
         let mut state = GameState::random();
         while !gamerules::is_game_over(&state) {
-            let action = if state.ply as usize % 2 == first_player {
-                instance_one.on_move_request(&state)
+            let (client, instance) = if state.ply as usize % 2 == first_player {
+                (0, &mut instance_one)
             } else {
-                instance_two.on_move_request(&state)
+                (1, &mut instance_two)
             };
+            let action = instance.on_move_request(&state);
+            if let Some(info) = &instance.last_info {
+                test_result.lock().unwrap().add_search_info(client, info);
+            }
             gamerules::do_action(&mut state, action);
         }
         let mut game_result = gamerules::game_result(&state);
@@ -135,14 +183,20 @@ pub fn run_test(
 fn main() {
     let path_one = String::from("target/release/client.exe");
     let path_two = String::from("clients/cb.exe");
+    // Additional arguments for each client, e.g. ["--parameters", "parameters.json"]
+    let args_one: Vec<String> = Vec::new();
+    let args_two: Vec<String> = Vec::new();
+    // Clients that print info lines also report their average search depth
+    let info_lines_one = false;
+    let info_lines_two = false;
     let threads: usize = 8;
 
     let test_result = Arc::new(Mutex::new(TestResult::default()));
     let mut handles: Vec<JoinHandle<()>> = Vec::with_capacity(8);
     for _ in 0..threads {
         handles.push(run_test(
-            path_one.clone(),
-            path_two.clone(),
+            (path_one.clone(), args_one.clone(), info_lines_one),
+            (path_two.clone(), args_two.clone(), info_lines_two),
             Arc::clone(&test_result),
         ));
     }
@@ -0,0 +1,212 @@
+use argparse::{ArgumentParser, Store};
+use game_sdk::gamerules;
+use game_sdk::gamestate::GameState;
+use player::minimax::evaluation::{static_evaluation, EvaluationParameters, MATE_VALUE};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::thread;
+use std::time::Instant;
+
+const MIN_STEP: f32 = 0.01;
+
+pub struct Position {
+    state: GameState,
+    result: f32,
+}
+
+// Every line of the dataset contains a FEN and the final game result from the perspective
+// of red (1, 0 or -1), separated by semicolons. Fields in between are ignored.
+pub fn load_dataset(path: &str, parameters: &EvaluationParameters) -> Vec<Position> {
+    let file = File::open(path).unwrap_or_else(|_| panic!("Can't open the dataset: {}", path));
+    let mut positions = Vec::new();
+    for line in BufReader::new(file).lines() {
+        let line = line.unwrap();
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let fields: Vec<&str> = line.split(';').collect();
+        let state = GameState::from_fen(fields[0]);
+        let result = fields[fields.len() - 1]
+            .trim()
+            .parse::<i16>()
+            .unwrap_or_else(|_| panic!("Invalid game result: {}", line));
+        // Finished games and forced wins don't depend on the weights
+        if gamerules::is_game_over(&state)
+            || static_evaluation(&state, parameters).abs() >= MATE_VALUE
+        {
+            continue;
+        }
+        positions.push(Position {
+            state,
+            result: (result.signum() as f32 + 1.0) / 2.0,
+        });
+    }
+    positions
+}
+
+#[inline(always)]
+fn sigmoid(value: f32, k: f32) -> f32 {
+    1.0 / (1.0 + 10f32.powf(-k * value / 400.0))
+}
+
+pub fn mean_squared_error(
+    positions: &[Position],
+    parameters: &EvaluationParameters,
+    k: f32,
+    threads: usize,
+) -> f32 {
+    let chunk_size = (positions.len() / threads).max(1);
+    let sum: f64 = thread::scope(|scope| {
+        let handles: Vec<_> = positions
+            .chunks(chunk_size)
+            .map(|chunk| {
+                scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .map(|position| {
+                            let value = static_evaluation(&position.state, parameters) as f32;
+                            let error = position.result - sigmoid(value, k);
+                            (error * error) as f64
+                        })
+                        .sum::<f64>()
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .sum()
+    });
+    (sum / positions.len() as f64) as f32
+}
+
+// Finds the scaling constant that fits the current evaluation best
+pub fn find_k(positions: &[Position], parameters: &EvaluationParameters, threads: usize) -> f32 {
+    let mut best_k = 1.0;
+    let mut best_error = mean_squared_error(positions, parameters, best_k, threads);
+    let mut step = 0.5;
+    while step >= 0.001 {
+        let mut improved = true;
+        while improved {
+            improved = false;
+            for k in [best_k + step, best_k - step] {
+                if k <= 0.0 {
+                    continue;
+                }
+                let error = mean_squared_error(positions, parameters, k, threads);
+                if error < best_error {
+                    best_error = error;
+                    best_k = k;
+                    improved = true;
+                }
+            }
+        }
+        step /= 10.0;
+    }
+    best_k
+}
+
+pub fn tune(
+    positions: &[Position],
+    parameters: &mut EvaluationParameters,
+    k: f32,
+    max_iterations: usize,
+    threads: usize,
+) -> f32 {
+    let mut best_error = mean_squared_error(positions, parameters, k, threads);
+    let mut steps: Vec<f32> = parameters
+        .weights_mut()
+        .iter()
+        .map(|weight| (weight.abs() * 0.1).max(0.5))
+        .collect();
+    for iteration in 1..=max_iterations {
+        let start_time = Instant::now();
+        let mut improved = false;
+        for (i, step) in steps.iter_mut().enumerate() {
+            for delta in [*step, -*step] {
+                *parameters.weights_mut()[i] += delta;
+                let error = mean_squared_error(positions, parameters, k, threads);
+                if error < best_error {
+                    best_error = error;
+                    improved = true;
+                    break;
+                }
+                *parameters.weights_mut()[i] -= delta;
+            }
+        }
+        println!(
+            "Iteration {:4} Error: {:.8} Elapsed: {:6}ms",
+            iteration,
+            best_error,
+            start_time.elapsed().as_millis()
+        );
+        if !improved {
+            if steps.iter().all(|step| *step <= MIN_STEP) {
+                break;
+            }
+            for step in steps.iter_mut() {
+                *step = (*step / 2.0).max(MIN_STEP);
+            }
+        }
+    }
+    best_error
+}
+
+fn main() {
+    let mut input = "dataset.txt".to_string();
+    let mut output = "parameters.json".to_string();
+    let mut initial_parameters = "".to_string();
+    let mut iterations: usize = 1000;
+    let mut threads: usize = 1;
+
+    {
+        let mut parser = ArgumentParser::new();
+        parser
+            .refer(&mut input)
+            .add_option(&["-i", "--input"], Store, "Dataset");
+        parser
+            .refer(&mut output)
+            .add_option(&["-o", "--output"], Store, "Output parameter file");
+        parser.refer(&mut initial_parameters).add_option(
+            &["-e", "--parameters"],
+            Store,
+            "Initial parameter file",
+        );
+        parser.refer(&mut iterations).add_option(
+            &["-n", "--iterations"],
+            Store,
+            "Maximum number of iterations",
+        );
+        parser
+            .refer(&mut threads)
+            .add_option(&["-j", "--threads"], Store, "Number of threads");
+        parser.parse_args_or_exit();
+    }
+
+    let mut parameters = if initial_parameters.is_empty() {
+        EvaluationParameters::default()
+    } else {
+        EvaluationParameters::load(&initial_parameters)
+            .unwrap_or_else(|e| panic!("Can't load the parameters {}: {}", initial_parameters, e))
+    };
+    let threads = threads.max(1);
+    let positions = load_dataset(&input, &parameters);
+    if positions.is_empty() {
+        println!("The dataset doesn't contain any usable positions.");
+        return;
+    }
+    println!("Loaded {} positions from {}", positions.len(), input);
+    let k = find_k(&positions, &parameters, threads);
+    let initial_error = mean_squared_error(&positions, &parameters, k, threads);
+    println!("K: {:.3} Initial error: {:.8}", k, initial_error);
+    let final_error = tune(&positions, &mut parameters, k, iterations, threads);
+    println!(
+        "Error before: {:.8} Error after: {:.8}",
+        initial_error, final_error
+    );
+    parameters
+        .save(&output)
+        .unwrap_or_else(|e| panic!("Can't save the parameters to {}: {}", output, e));
+    println!("Saved the parameters to {}", output);
+}